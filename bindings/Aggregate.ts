@@ -0,0 +1,13 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AggregateFunction } from "./AggregateFunction";
+
+export type Aggregate = { function: AggregateFunction, 
+/**
+ * The column to aggregate. Only `Count` can leave it out, to count the
+ * rows.
+ */
+column: string | null, 
+/**
+ * The name of the result column. Defaults to e.g. `sum_price`.
+ */
+name: string | null, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A column that was added, removed or changed type. `before` is `None` for
+ * added columns and `after` is `None` for removed ones.
+ */
+export type ColumnChange = { column: string, before: string | null, after: string | null, };
@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ColumnType } from "./ColumnType";
+
+/**
+ * A column of a query, as described by DuckDB.
+ */
+export type ColumnSchema = { name: string, 
+/**
+ * The type as DuckDB names it, e.g. `STRUCT(x INTEGER, y VARCHAR[])`.
+ */
+data_type: string, column_type: ColumnType, nullable: boolean, 
+/**
+ * Set with `COMMENT ON COLUMN`.
+ */
+comment: string | null, };
@@ -0,0 +1,33 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ColumnType } from "./ColumnType";
+import type { Quantiles } from "./Quantiles";
+import type { ValueCount } from "./ValueCount";
+
+/**
+ * Statistics of a column over the rows matching a request's filters.
+ */
+export type ColumnSummary = { name: string, column_type: ColumnType, 
+/**
+ * The number of rows, nulls included.
+ */
+count: bigint, null_count: bigint, 
+/**
+ * `null_count` over `count`, 0 when there are no rows.
+ */
+null_ratio: number, 
+/**
+ * Estimated with HyperLogLog, so it can be off by a few percent.
+ */
+approx_distinct: bigint, 
+/**
+ * As DuckDB prints them, since not every type compares as JSON.
+ */
+min: string | null, max: string | null, 
+/**
+ * Only set for numbers.
+ */
+mean: number | null, std: number | null, quantiles: Quantiles | null, 
+/**
+ * The most frequent values other than null, most frequent first.
+ */
+top_values: Array<ValueCount>, };
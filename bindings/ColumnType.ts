@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Field } from "./Field";
+import type { TimeUnit } from "./TimeUnit";
+
+/**
+ * A DuckDB logical type.
+ */
+export type ColumnType = { "type": "Boolean" } | { "type": "TinyInt" } | { "type": "SmallInt" } | { "type": "Integer" } | { "type": "BigInt" } | { "type": "HugeInt" } | { "type": "UTinyInt" } | { "type": "USmallInt" } | { "type": "UInteger" } | { "type": "UBigInt" } | { "type": "UHugeInt" } | { "type": "VarInt" } | { "type": "Float" } | { "type": "Double" } | { "type": "Decimal", precision: number, scale: number, } | { "type": "Varchar" } | { "type": "Blob" } | { "type": "Bit" } | { "type": "Uuid" } | { "type": "Date" } | { "type": "Time", with_time_zone: boolean, } | { "type": "Timestamp", unit: TimeUnit, 
+/**
+ * The time zone values are given in, for `TIMESTAMP WITH TIME ZONE`.
+ */
+time_zone: string | null, } | { "type": "Interval" } | { "type": "Enum", values: Array<string>, } | { "type": "List", element: ColumnType, } | { "type": "Array", element: ColumnType, size: number, } | { "type": "Struct", fields: Array<Field>, } | { "type": "Map", key: ColumnType, value: ColumnType, } | { "type": "Union", members: Array<Field>, } | { "type": "Other", name: string, };
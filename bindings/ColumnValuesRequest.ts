@@ -0,0 +1,27 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ComputedColumn } from "./ComputedColumn";
+import type { Filter } from "./Filter";
+
+export type ColumnValuesRequest = { name: string, 
+/**
+ * A column, or a dotted path into a struct column as in `Ordering`.
+ */
+column: string, filter: Filter | null, raw_filter: string | null, params: { [key in string]?: string } | null, columns: Array<string> | null, computed_columns: Array<ComputedColumn> | null, 
+/**
+ * Leave out the conditions of `filter` on the column itself, so that
+ * the values are those the filter could be changed to select.
+ */
+exclude_column_filter: boolean | null, 
+/**
+ * Only return values whose text starts with this, ignoring case.
+ */
+prefix: string | null, 
+/**
+ * How many values to return at most. Defaults to 100.
+ */
+limit: number | null, 
+/**
+ * How many histogram buckets to return for a numeric or temporal
+ * column. Defaults to 10; 0 for none.
+ */
+buckets: number | null, };
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { HistogramBucket } from "./HistogramBucket";
+import type { ValueCount } from "./ValueCount";
+
+export type ColumnValuesResponse = { values: Array<ValueCount>, has_more: boolean, histogram: Array<HistogramBucket> | null, };
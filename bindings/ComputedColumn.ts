@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A column computed for a single request.
+ */
+export type ComputedColumn = { name: string, 
+/**
+ * A scalar SQL expression over the columns of the query, such as
+ * `price * qty`.
+ */
+expression: string, };
@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FilterOp } from "./FilterOp";
+import type { FilterValue } from "./FilterValue";
+
+export type Condition = { 
+/**
+ * The column, followed by the fields to descend into for struct
+ * columns, e.g. `["address", "city"]`.
+ */
+path: Array<string>, op: FilterOp, 
+/**
+ * The operand. Required by every operator except `IsNull` and
+ * `IsNotNull`; `In` and `NotIn` take a `List`.
+ */
+value: FilterValue | null, };
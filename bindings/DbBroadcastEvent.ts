@@ -1,3 +1,4 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ColumnChange } from "./ColumnChange";
 
-export type DbBroadcastEvent = { "eventType": "Ping", data: string, } | { "eventType": "QueryUpdated", name: string, };
+export type DbBroadcastEvent = { "eventType": "Ping", data: string, } | { "eventType": "QueryUpdated", name: string, } | { "eventType": "SchemaChanged", name: string, columns: Array<ColumnChange>, } | { "eventType": "TotalCount", name: string, count_token: string, total_count: number, };
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Graph } from "./Graph";
+
+export type DependencyGraphResponse = { graph: Graph, 
+/**
+ * The same graph in Graphviz DOT format.
+ */
+dot: string, };
@@ -1,4 +1,40 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ComputedColumn } from "./ComputedColumn";
+import type { CountMode } from "./CountMode";
+import type { Filter } from "./Filter";
 import type { Ordering } from "./Ordering";
 
-export type ExecQueryRequest = { name: string, page: number | null, page_size: number | null, order_by: Array<Ordering> | null, filters: string, };
+export type ExecQueryRequest = { name: string, page: number | null, page_size: number | null, order_by: Array<Ordering> | null, filter: Filter | null, 
+/**
+ * A SQL boolean expression, only accepted when the server runs with
+ * `--allow-raw-filters`.
+ */
+raw_filter: string | null, params: { [key in string]?: string } | null, 
+/**
+ * Page by cursor instead of by page number, with the ordering made
+ * unique by the query's key columns. `page` is then ignored.
+ */
+keyset: boolean | null, 
+/**
+ * The `next_cursor` or `prev_cursor` of a previous response, to fetch
+ * the page after or before it. Implies `keyset`.
+ */
+cursor: string | null, 
+/**
+ * How to count the rows matching the filters. Defaults to `Exact`.
+ */
+count: CountMode | null, 
+/**
+ * The columns to return, in order. All of them by default.
+ */
+columns: Array<string> | null, 
+/**
+ * Columns computed from the others, added after them. Only accepted
+ * when the server runs with `--allow-computed-columns`.
+ */
+computed_columns: Array<ComputedColumn> | null, 
+/**
+ * The ETag of a previous response. If the results have not changed
+ * since, the response is `not_modified` and carries no data.
+ */
+if_none_match: string | null, };
@@ -1,4 +1,36 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Field } from "./Field";
 import type { JsonValue } from "./serde_json/JsonValue";
 
-export type ExecQueryResponse = { total_count: number, data: Array<Array<JsonValue>>, schema: JsonValue, };
+export type ExecQueryResponse = { 
+/**
+ * `None` in `Deferred` count mode until the count is known.
+ */
+total_count: number | null, total_count_estimated: boolean, 
+/**
+ * In `Deferred` count mode, identifies the count for the `TotalCount`
+ * RPC and event.
+ */
+count_token: string | null, data: Array<Array<JsonValue>>, 
+/**
+ * The name and type of each column of `data`.
+ */
+schema: Array<Field>, 
+/**
+ * In keyset mode, the cursors of the next and previous pages, if there
+ * are any.
+ */
+next_cursor: string | null, prev_cursor: string | null, warnings: Array<string>, 
+/**
+ * Changes whenever the results may have changed.
+ */
+etag: string, 
+/**
+ * Whether the results still match the request's `if_none_match`, in
+ * which case `total_count`, `data` and `schema` are left empty.
+ */
+not_modified: boolean, 
+/**
+ * Whether the page was served from the result cache.
+ */
+from_cache: boolean, };
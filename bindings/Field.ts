@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ColumnType } from "./ColumnType";
+
+/**
+ * A field of a struct, or a member of a union.
+ */
+export type Field = { name: string, column_type: ColumnType, };
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Condition } from "./Condition";
+
+/**
+ * A filter on the rows of a query result, compiled to a parameterized
+ * `WHERE` clause.
+ */
+export type Filter = { "type": "And", filters: Array<Filter>, } | { "type": "Or", filters: Array<Filter>, } | { "type": "Not", filter: Filter, } | { "type": "Condition" } & Condition;
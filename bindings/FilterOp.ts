@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type FilterOp = "Eq" | "NotEq" | "Lt" | "LtEq" | "Gt" | "GtEq" | "Like" | "NotLike" | "ILike" | "In" | "NotIn" | "IsNull" | "IsNotNull";
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type FilterValue = { "type": "Null" } | { "type": "Boolean", "value": boolean } | { "type": "Integer", "value": number } | { "type": "Float", "value": number } | { "type": "Text", "value": string } | { "type": "List", "value": Array<FilterValue> };
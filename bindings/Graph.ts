@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { GraphEdge } from "./GraphEdge";
+import type { GraphNode } from "./GraphNode";
+
+/**
+ * Sources, initializers and queries, and how data flows between them.
+ */
+export type Graph = { nodes: Array<GraphNode>, edges: Array<GraphEdge>, };
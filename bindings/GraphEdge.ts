@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * `to` reads from what `from` provides.
+ */
+export type GraphEdge = { from: string, to: string, };
@@ -0,0 +1,13 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { NodeKind } from "./NodeKind";
+
+export type GraphNode = { 
+/**
+ * Unique id, such as `source:users` or `query:active_users`.
+ */
+id: string, kind: NodeKind, name: string, 
+/**
+ * RFC 3339 timestamp of the last refresh of a source, or the last run of
+ * an initializer or query.
+ */
+last_run_at: string | null, last_run_ms: bigint | null, error: string | null, };
@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { JsonValue } from "./serde_json/JsonValue";
+
+export type HistogramBucket = { 
+/**
+ * `lower` is inclusive, `upper` only for the last bucket. Dates are
+ * rounded down to the day.
+ */
+lower: JsonValue, upper: JsonValue, count: bigint, };
@@ -1,4 +1,18 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { Direction } from "./Direction";
+import type { Nulls } from "./Nulls";
 
-export type Ordering = { column: string, direction: Direction, };
+export type Ordering = { 
+/**
+ * A column of the query, or a dotted path into a struct column such as
+ * `address.city`. A column whose name contains dots takes precedence.
+ */
+column: string, direction: Direction, 
+/**
+ * Where NULLs go. Defaults to DuckDB's `NULLS LAST`.
+ */
+nulls: Nulls | null, 
+/**
+ * Compare text case-insensitively.
+ */
+case_insensitive: boolean, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Approximate quartiles.
+ */
+export type Quantiles = { q25: number, q50: number, q75: number, };
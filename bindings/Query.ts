@@ -1,4 +1,13 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { QuerySource } from "./QuerySource";
 
-export type Query = { name: string, source: QuerySource, };
+export type Query = { name: string, source: QuerySource, 
+/**
+ * Cancel executions that run longer than this.
+ */
+timeout_secs: bigint | null, 
+/**
+ * Columns that together identify a row, used to order rows
+ * unambiguously when paging by cursor.
+ */
+key_columns: Array<string>, };
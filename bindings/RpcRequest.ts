@@ -1,4 +1,10 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AggregateQueryRequest } from "./AggregateQueryRequest";
+import type { ColumnValuesRequest } from "./ColumnValuesRequest";
+import type { DescribeQueryRequest } from "./DescribeQueryRequest";
 import type { ExecQueryRequest } from "./ExecQueryRequest";
+import type { QueryLineageRequest } from "./QueryLineageRequest";
+import type { SummarizeQueryRequest } from "./SummarizeQueryRequest";
+import type { TotalCountRequest } from "./TotalCountRequest";
 
-export type RpcRequest = { "rpcType": "ListQueries" } | { "rpcType": "ExecQuery" } & ExecQueryRequest;
+export type RpcRequest = { "rpcType": "ListQueries" } | { "rpcType": "ListSources" } | { "rpcType": "DependencyGraph" } | { "rpcType": "QueryLineage" } & QueryLineageRequest | { "rpcType": "DescribeQuery" } & DescribeQueryRequest | { "rpcType": "SummarizeQuery" } & SummarizeQueryRequest | { "rpcType": "ColumnValues" } & ColumnValuesRequest | { "rpcType": "TotalCount" } & TotalCountRequest | { "rpcType": "CacheStats" } | { "rpcType": "ExecQuery" } & ExecQueryRequest | { "rpcType": "AggregateQuery" } & AggregateQueryRequest;
@@ -1,5 +1,13 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CacheStatsResponse } from "./CacheStatsResponse";
+import type { ColumnValuesResponse } from "./ColumnValuesResponse";
+import type { DependencyGraphResponse } from "./DependencyGraphResponse";
+import type { DescribeQueryResponse } from "./DescribeQueryResponse";
 import type { ExecQueryResponse } from "./ExecQueryResponse";
 import type { ListQueriesResponse } from "./ListQueriesResponse";
+import type { ListSourcesResponse } from "./ListSourcesResponse";
+import type { QueryLineageResponse } from "./QueryLineageResponse";
+import type { SummarizeQueryResponse } from "./SummarizeQueryResponse";
+import type { TotalCountResponse } from "./TotalCountResponse";
 
-export type RpcResponse = { "rpcType": "ListQueries" } & ListQueriesResponse | { "rpcType": "ExecQuery" } & ExecQueryResponse;
+export type RpcResponse = { "rpcType": "ListQueries" } & ListQueriesResponse | { "rpcType": "ListSources" } & ListSourcesResponse | { "rpcType": "DependencyGraph" } & DependencyGraphResponse | { "rpcType": "QueryLineage" } & QueryLineageResponse | { "rpcType": "DescribeQuery" } & DescribeQueryResponse | { "rpcType": "SummarizeQuery" } & SummarizeQueryResponse | { "rpcType": "ColumnValues" } & ColumnValuesResponse | { "rpcType": "TotalCount" } & TotalCountResponse | { "rpcType": "CacheStats" } & CacheStatsResponse | { "rpcType": "ExecQuery" } & ExecQueryResponse | { "rpcType": "AggregateQuery" } & ExecQueryResponse;
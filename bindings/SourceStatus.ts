@@ -0,0 +1,27 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type SourceStatus = { name: string, 
+/**
+ * RFC 3339 timestamp of the last refresh attempt.
+ */
+last_refreshed_at: string | null, last_refresh_ms: bigint | null, 
+/**
+ * The error of the last refresh, if it failed.
+ */
+error: string | null, 
+/**
+ * Number of malformed records dropped during the last successful
+ * refresh.
+ */
+rejects_count: bigint, 
+/**
+ * The `<table>__rejects` views holding them, for the tables of the source
+ * that had any.
+ */
+rejects_views: Array<string>, 
+/**
+ * Number of lines of a text source that did not match its pattern during
+ * the last successful refresh. They are kept with only `_line` and
+ * `_raw` set.
+ */
+unmatched_lines: bigint, };
@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ComputedColumn } from "./ComputedColumn";
+import type { Filter } from "./Filter";
+
+export type SummarizeQueryRequest = { name: string, 
+/**
+ * Only the rows matching the filters are summarized, as in
+ * `ExecQuery`.
+ */
+filter: Filter | null, raw_filter: string | null, params: { [key in string]?: string } | null, columns: Array<string> | null, computed_columns: Array<ComputedColumn> | null, 
+/**
+ * How many of the most frequent values to return for each column.
+ * Defaults to 5.
+ */
+top_k: number | null, };
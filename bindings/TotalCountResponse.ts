@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type TotalCountResponse = { 
+/**
+ * `None` while the rows are still being counted.
+ */
+total_count: number | null, };
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { LineageKind } from "./LineageKind";
+
+export type UpstreamColumn = { 
+/**
+ * The source table (or initializer table) the column belongs to.
+ */
+table: string, column: string, kind: LineageKind, };
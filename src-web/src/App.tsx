@@ -10,6 +10,9 @@ import { ErrorTable } from "./ErrorTable";
 import DataTable from "./DataTable";
 import QueryList from "./QueryList";
 import { Ordering } from "./bindings/Ordering";
+import { ExecQueryResponse } from "./bindings/ExecQueryResponse";
+import { Field } from "./bindings/Field";
+import { parseFilter } from "./filter";
 
 function fetchQuery(
   name: string,
@@ -17,16 +20,28 @@ function fetchQuery(
   pageSize: number,
   ordering: Ordering[],
   filters: string,
+  ifNoneMatch: string | null,
   signal?: AbortSignal,
 ) {
+  // Raw SQL filters are disabled unless the server allows them, so only send
+  // what cannot be expressed as a structured filter that way.
+  const filter = filters ? parseFilter(filters) : null;
   return rpc(
     "ExecQuery",
     {
       name,
       page,
-      filters,
+      filter,
+      raw_filter: filters && !filter ? filters : null,
       page_size: pageSize,
       order_by: ordering,
+      params: null,
+      keyset: null,
+      cursor: null,
+      count: null,
+      columns: null,
+      computed_columns: null,
+      if_none_match: ifNoneMatch,
     },
     signal,
   );
@@ -36,10 +51,6 @@ function fetchQueryList() {
   return rpc("ListQueries", {});
 }
 
-type JsonObject = {
-  [key: string]: JsonValue | undefined;
-};
-
 function App() {
   const [queries, setQueries] = useState<string[]>([]);
   const [selectedQuery, setSelectedQuery] = useState<string | null>(null);
@@ -52,10 +63,12 @@ function App() {
   const [dataFetchedAt, setDataFetchedAt] = useState<Date>(new Date());
   const [totalCount, setTotalCount] = useState<number>(0);
   const [duration, setDuration] = useState<number>(0);
-  const [schema, setSchema] = useState<JsonObject[] | null>(null);
+  const [schema, setSchema] = useState<Field[] | null>(null);
   const [error, setError] = useState<Error | null>(null);
 
   const suppressQueryEvents = useRef(false);
+  // The last response, shown again when the server says it is unchanged.
+  const lastResponse = useRef<ExecQueryResponse | null>(null);
 
   if (!selectedQuery && queries.length > 0) {
     setSelectedQuery(queries[0]);
@@ -80,22 +93,24 @@ function App() {
       pageSize,
       sortBy,
       filterString,
+      lastResponse.current?.etag ?? null,
       abortController.signal,
     )
-      .then((data) => {
+      .then((response) => {
+        const data =
+          response.not_modified && lastResponse.current
+            ? lastResponse.current
+            : response;
+        lastResponse.current = data;
         const duration = performance.now() - startTime;
         setDuration(duration);
         setData(data.data);
-        setTotalCount(data.total_count);
+        setTotalCount(data.total_count ?? 0);
         setError(null);
-        if (typeof data.schema == "object" && !Array.isArray(data.schema)) {
-          if (Array.isArray(data.schema?.fields)) {
-            if (abortController.signal.aborted) {
-              return;
-            }
-            setSchema(data.schema.fields as JsonObject[]);
-          }
+        if (abortController.signal.aborted) {
+          return;
         }
+        setSchema(data.schema);
       })
       .catch((e) => {
         if (abortController.signal.aborted) {
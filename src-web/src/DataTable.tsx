@@ -7,10 +7,6 @@ import { Ordering } from "./bindings/Ordering";
 import { Direction } from "./bindings/Direction";
 import { TimeDistanceFromNow } from "./TimeDistance";
 
-type JsonObject = {
-  [key: string]: JsonValue | undefined;
-};
-
 import {
   ColumnDef,
   getCoreRowModel,
@@ -42,14 +38,16 @@ import {
 
 import { DraggableTableHeader } from "./DraggableTableHeader";
 import { DataCell } from "./DataCell";
+import { ColumnLineage } from "./bindings/ColumnLineage";
+import { Field } from "./bindings/Field";
+import { rpc } from "./rpc";
 import { ContextMenuContext } from "./ContextMenu";
 
 function makeColumns(
-  schema: JsonObject[],
+  schema: Field[],
 ): ColumnDef<JsonValue[], JsonValue[]>[] {
   const columnHelper = createColumnHelper<JsonValue[]>();
-  return schema.map((f, idx) => {
-    const field = f as { name: string };
+  return schema.map((field, idx) => {
     return columnHelper.accessor((row) => row[idx], {
       header: field.name,
       id: idx.toString(),
@@ -58,10 +56,9 @@ function makeColumns(
   });
 }
 
-function defaultColumnVisibility(schema: JsonObject[]) {
+function defaultColumnVisibility(schema: Field[]) {
   const columnVisibility: { [key: string]: boolean } = {};
-  schema.forEach((f, idx) => {
-    const field = f as { name: string };
+  schema.forEach((field, idx) => {
     if (field.name.startsWith("__")) {
       columnVisibility[idx.toString()] = false;
     } else {
@@ -87,7 +84,7 @@ function DataTable({
   dataFetchedAt,
 }: {
   data: JsonValue[][];
-  schema: JsonObject[];
+  schema: Field[];
   queryName: string;
   duration: number;
   totalCount: number;
@@ -113,6 +110,17 @@ function DataTable({
     return res;
   });
 
+  const [lineage, setLineage] = useState<ColumnLineage[] | null>(null);
+
+  useEffect(() => {
+    rpc("QueryLineage", { name: queryName })
+      .then((data) => setLineage(data.columns))
+      .catch((e) => {
+        console.error(e);
+        setLineage(null);
+      });
+  }, [queryName]);
+
   useEffect(() => {
     saveColumnOrderToLocalStorage(queryName, columnOrder);
   }, [columnOrder, queryName]);
@@ -140,8 +148,10 @@ function DataTable({
           const direction: Direction = sort.desc ? "Desc" : "Asc";
           if (column) {
             return {
-              column: column.name as string,
+              column: column.name,
               direction: direction,
+              nulls: null,
+              case_insensitive: false,
             };
           }
         })
@@ -237,7 +247,7 @@ function DataTable({
           <TbFilter className="absolute inline-block size-4" />
           <input
             type="text"
-            placeholder="Type a WHERE clause here. CMD+Enter to re-run query."
+            placeholder="Filter, e.g. age >= 18 AND name LIKE 'A%'. CMD+Enter to re-run query."
             className="w-full rounded-md bg-gray-50 pl-5"
             onKeyDown={(e) => {
               if (e.key === "Enter" && e.metaKey) {
@@ -272,6 +282,7 @@ function DataTable({
                       key={header.id}
                       header={header}
                       numSortedColumns={sorting.length}
+                      lineage={lineage?.[Number(header.column.id)]}
                     />
                   ))}
                 </SortableContext>
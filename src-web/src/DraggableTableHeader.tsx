@@ -1,5 +1,6 @@
 import { CSSProperties, useState } from "react";
 import { JsonValue } from "./bindings/serde_json/JsonValue";
+import { ColumnLineage } from "./bindings/ColumnLineage";
 import {
   TbSortAscendingLetters,
   TbSortDescendingLetters,
@@ -13,12 +14,25 @@ import { CSS } from "@dnd-kit/utilities";
 
 const CLICK_DURATION = 200;
 
+function describeLineage(lineage: ColumnLineage | undefined) {
+  if (!lineage || lineage.upstream.length === 0) {
+    return "";
+  }
+  const lines = lineage.upstream.map(
+    (column) =>
+      `  ${column.table}.${column.column}${column.kind === "Derived" ? " (derived)" : ""}`,
+  );
+  return `\n\nFrom:\n${lines.join("\n")}`;
+}
+
 export function DraggableTableHeader({
   header,
   numSortedColumns,
+  lineage,
 }: {
   header: Header<JsonValue[], unknown>;
   numSortedColumns: number;
+  lineage?: ColumnLineage;
 }) {
   const { attributes, isDragging, listeners, setNodeRef, transform } =
     useSortable({
@@ -81,10 +95,10 @@ export function DraggableTableHeader({
         title={
           header.column.getCanSort()
             ? header.column.getNextSortingOrder() === "asc"
-              ? `${header.column.columnDef.header}\n\nClick to sort ascending.\n\nShift+click to sort multiple columns.`
+              ? `${header.column.columnDef.header}${describeLineage(lineage)}\n\nClick to sort ascending.\n\nShift+click to sort multiple columns.`
               : header.column.getNextSortingOrder() === "desc"
-                ? `${header.column.columnDef.header}\n\nClick to sort descending`
-                : `${header.column.columnDef.header}\n\nClick to clear sort`
+                ? `${header.column.columnDef.header}${describeLineage(lineage)}\n\nClick to sort descending`
+                : `${header.column.columnDef.header}${describeLineage(lineage)}\n\nClick to clear sort`
             : undefined
         }
         {...dragAttrs}
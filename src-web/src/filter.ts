@@ -0,0 +1,240 @@
+import { Filter } from "./bindings/Filter";
+import { FilterOp } from "./bindings/FilterOp";
+import { FilterValue } from "./bindings/FilterValue";
+
+type Token =
+  | { kind: "word"; text: string }
+  | { kind: "quoted"; text: string }
+  | { kind: "string"; text: string }
+  | { kind: "number"; text: string }
+  | { kind: "symbol"; text: string };
+
+const COMPARISONS: Record<string, FilterOp> = {
+  "=": "Eq",
+  "==": "Eq",
+  "!=": "NotEq",
+  "<>": "NotEq",
+  "<": "Lt",
+  "<=": "LtEq",
+  ">": "Gt",
+  ">=": "GtEq",
+};
+
+function tokenize(text: string): Token[] | null {
+  const tokens: Token[] = [];
+  let i = 0;
+  while (i < text.length) {
+    const rest = text.slice(i);
+    const space = rest.match(/^\s+/);
+    if (space) {
+      i += space[0].length;
+      continue;
+    }
+    if (rest[0] === "'" || rest[0] === '"') {
+      // Quotes are escaped by doubling them, as in SQL.
+      const quote = rest[0];
+      let value = "";
+      let end = 1;
+      for (;;) {
+        if (end >= rest.length) return null;
+        if (rest[end] === quote) {
+          if (rest[end + 1] !== quote) break;
+          end++;
+        }
+        value += rest[end];
+        end++;
+      }
+      tokens.push({ kind: quote === "'" ? "string" : "quoted", text: value });
+      i += end + 1;
+      continue;
+    }
+    const number = rest.match(/^-?\d+(\.\d+)?([eE][+-]?\d+)?/);
+    if (number) {
+      tokens.push({ kind: "number", text: number[0] });
+      i += number[0].length;
+      continue;
+    }
+    const word = rest.match(/^[A-Za-z_][A-Za-z0-9_]*/);
+    if (word) {
+      tokens.push({ kind: "word", text: word[0] });
+      i += word[0].length;
+      continue;
+    }
+    const symbol = rest.match(/^(<=|>=|<>|!=|==|[=<>(),.])/);
+    if (symbol) {
+      tokens.push({ kind: "symbol", text: symbol[0] });
+      i += symbol[0].length;
+      continue;
+    }
+    return null;
+  }
+  return tokens;
+}
+
+class Parser {
+  private tokens: Token[];
+  private pos = 0;
+
+  constructor(tokens: Token[]) {
+    this.tokens = tokens;
+  }
+
+  atEnd() {
+    return this.pos >= this.tokens.length;
+  }
+
+  private peek(): Token | undefined {
+    return this.tokens[this.pos];
+  }
+
+  private keyword(...words: string[]): boolean {
+    const start = this.pos;
+    for (const word of words) {
+      const token = this.peek();
+      if (token?.kind !== "word" || token.text.toUpperCase() !== word) {
+        this.pos = start;
+        return false;
+      }
+      this.pos++;
+    }
+    return true;
+  }
+
+  private symbol(text: string): boolean {
+    const token = this.peek();
+    if (token?.kind === "symbol" && token.text === text) {
+      this.pos++;
+      return true;
+    }
+    return false;
+  }
+
+  or(): Filter | null {
+    const filters = [this.and()];
+    while (this.keyword("OR")) {
+      filters.push(this.and());
+    }
+    if (filters.some((filter) => filter === null)) return null;
+    return filters.length === 1
+      ? filters[0]
+      : { type: "Or", filters: filters as Filter[] };
+  }
+
+  private and(): Filter | null {
+    const filters = [this.not()];
+    while (this.keyword("AND")) {
+      filters.push(this.not());
+    }
+    if (filters.some((filter) => filter === null)) return null;
+    return filters.length === 1
+      ? filters[0]
+      : { type: "And", filters: filters as Filter[] };
+  }
+
+  private not(): Filter | null {
+    if (this.keyword("NOT")) {
+      const filter = this.not();
+      return filter && { type: "Not", filter };
+    }
+    if (this.symbol("(")) {
+      const filter = this.or();
+      return this.symbol(")") ? filter : null;
+    }
+    return this.condition();
+  }
+
+  private path(): string[] | null {
+    const path: string[] = [];
+    do {
+      const token = this.peek();
+      if (!token || (token.kind !== "word" && token.kind !== "quoted")) {
+        return null;
+      }
+      path.push(token.text);
+      this.pos++;
+    } while (this.symbol("."));
+    return path;
+  }
+
+  private condition(): Filter | null {
+    const path = this.path();
+    if (!path) return null;
+    const condition = (op: FilterOp, value: FilterValue | null): Filter => ({
+      type: "Condition",
+      path,
+      op,
+      value,
+    });
+
+    if (this.keyword("IS", "NOT", "NULL")) return condition("IsNotNull", null);
+    if (this.keyword("IS", "NULL")) return condition("IsNull", null);
+
+    const negated = this.keyword("NOT");
+    if (this.keyword("IN")) {
+      const values = this.list();
+      return values && condition(negated ? "NotIn" : "In", values);
+    }
+    if (this.keyword("LIKE") || (!negated && this.keyword("ILIKE"))) {
+      const op = this.tokens[this.pos - 1].text.toUpperCase();
+      const value = this.value();
+      if (value?.type !== "Text") return null;
+      if (op === "ILIKE") return condition("ILike", value);
+      return condition(negated ? "NotLike" : "Like", value);
+    }
+    if (negated) return null;
+
+    const token = this.peek();
+    const op = token?.kind === "symbol" ? COMPARISONS[token.text] : undefined;
+    if (!op) return null;
+    this.pos++;
+    const value = this.value();
+    return value && condition(op, value);
+  }
+
+  private list(): FilterValue | null {
+    if (!this.symbol("(")) return null;
+    const values: FilterValue[] = [];
+    do {
+      const value = this.value();
+      if (!value) return null;
+      values.push(value);
+    } while (this.symbol(","));
+    return this.symbol(")") ? { type: "List", value: values } : null;
+  }
+
+  private value(): FilterValue | null {
+    const token = this.peek();
+    if (!token) return null;
+    this.pos++;
+    if (token.kind === "string") return { type: "Text", value: token.text };
+    if (token.kind === "number") {
+      return /^-?\d+$/.test(token.text)
+        ? { type: "Integer", value: Number(token.text) }
+        : { type: "Float", value: Number(token.text) };
+    }
+    if (token.kind === "word") {
+      switch (token.text.toUpperCase()) {
+        case "TRUE":
+          return { type: "Boolean", value: true };
+        case "FALSE":
+          return { type: "Boolean", value: false };
+        case "NULL":
+          return { type: "Null" };
+      }
+    }
+    return null;
+  }
+}
+
+/**
+ * Parse a filter typed as a simple WHERE clause, e.g. `age >= 18 AND
+ * (name LIKE 'A%' OR city IN ('Paris', 'Lyon'))`, into a structured filter.
+ * Returns null for anything else, which can only be sent as a raw filter.
+ */
+export function parseFilter(text: string): Filter | null {
+  const tokens = tokenize(text);
+  if (!tokens || tokens.length === 0) return null;
+  const parser = new Parser(tokens);
+  const filter = parser.or();
+  return parser.atEnd() ? filter : null;
+}
@@ -0,0 +1,229 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+/// Most distinct values a pivot column can have, each adding a column per
+/// aggregate.
+pub const MAX_PIVOT_VALUES: usize = 100;
+
+/// Rows grouped by some columns, with aggregates computed over each group.
+#[derive(TS, Serialize, Deserialize, Debug, Clone)]
+pub struct Aggregation {
+    /// Columns, or dotted paths into struct columns, to group the rows by.
+    /// All the rows make a single group if empty.
+    pub group_by: Vec<String>,
+    pub aggregates: Vec<Aggregate>,
+    /// A column whose distinct values each get their own column for each
+    /// aggregate, named `{value}_{name}`.
+    pub pivot: Option<String>,
+}
+
+#[derive(TS, Serialize, Deserialize, Debug, Clone)]
+pub struct Aggregate {
+    pub function: AggregateFunction,
+    /// The column to aggregate. Only `Count` can leave it out, to count the
+    /// rows.
+    pub column: Option<String>,
+    /// The name of the result column. Defaults to e.g. `sum_price`.
+    pub name: Option<String>,
+}
+
+#[derive(TS, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(tag = "type")]
+pub enum AggregateFunction {
+    Count,
+    CountDistinct,
+    Sum,
+    Avg,
+    Min,
+    Max,
+    /// The interpolated value below which `fraction` of the values fall,
+    /// e.g. 0.5 for the median.
+    Percentile {
+        fraction: f64,
+    },
+}
+
+impl Aggregate {
+    pub fn name(&self) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        let function = match self.function {
+            AggregateFunction::Count => "count".to_string(),
+            AggregateFunction::CountDistinct => "count_distinct".to_string(),
+            AggregateFunction::Sum => "sum".to_string(),
+            AggregateFunction::Avg => "avg".to_string(),
+            AggregateFunction::Min => "min".to_string(),
+            AggregateFunction::Max => "max".to_string(),
+            AggregateFunction::Percentile { fraction } => percentile_name(fraction),
+        };
+        match &self.column {
+            Some(column) => format!("{}_{}", function, column),
+            None => function,
+        }
+    }
+
+    /// The aggregate over `column`, the SQL of `self.column`, restricted to
+    /// the rows matching `condition` if any.
+    pub fn to_sql(&self, column: Option<&str>, condition: Option<&str>) -> anyhow::Result<String> {
+        let filter = match condition {
+            Some(condition) => format!(" FILTER (WHERE {})", condition),
+            None => "".to_string(),
+        };
+        let Some(column) = column else {
+            return match self.function {
+                AggregateFunction::Count => Ok(format!("COUNT(*){}", filter)),
+                function => Err(anyhow::anyhow!("Aggregate `{:?}` needs a column", function)),
+            };
+        };
+        Ok(match self.function {
+            AggregateFunction::Count => format!("COUNT({}){}", column, filter),
+            AggregateFunction::CountDistinct => format!("COUNT(DISTINCT {}){}", column, filter),
+            AggregateFunction::Sum => format!("SUM({}){}", column, filter),
+            AggregateFunction::Avg => format!("AVG({}){}", column, filter),
+            AggregateFunction::Min => format!("MIN({}){}", column, filter),
+            AggregateFunction::Max => format!("MAX({}){}", column, filter),
+            AggregateFunction::Percentile { fraction } => {
+                if !(0.0..=1.0).contains(&fraction) {
+                    return Err(anyhow::anyhow!(
+                        "Invalid percentile {}: it must be between 0 and 1",
+                        fraction
+                    ));
+                }
+                format!("quantile_cont({}, {}){}", column, fraction, filter)
+            }
+        })
+    }
+}
+
+/// Names a percentile from the digits of its fraction, e.g. `p07` for 0.07
+/// and `p99_9` for 0.999, since `0.07 * 100.0` is `7.000000000000001`.
+fn percentile_name(fraction: f64) -> String {
+    let digits = fraction.to_string();
+    let (units, decimals) = digits.split_once('.').unwrap_or((&digits, ""));
+    let decimals = format!("{:0<2}", decimals);
+    let (hundredths, rest) = decimals.split_at(2);
+    let units = units.trim_start_matches('0');
+    match rest {
+        "" => format!("p{}{}", units, hundredths),
+        rest => format!("p{}{}_{}", units, hundredths, rest),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aggregate(json: &str) -> Aggregate {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn compiles_each_function() {
+        let cases = [
+            (r#"{"type": "Count"}"#, r#"COUNT("price")"#),
+            (r#"{"type": "CountDistinct"}"#, r#"COUNT(DISTINCT "price")"#),
+            (r#"{"type": "Sum"}"#, r#"SUM("price")"#),
+            (r#"{"type": "Avg"}"#, r#"AVG("price")"#),
+            (r#"{"type": "Min"}"#, r#"MIN("price")"#),
+            (r#"{"type": "Max"}"#, r#"MAX("price")"#),
+            (
+                r#"{"type": "Percentile", "fraction": 0.9}"#,
+                r#"quantile_cont("price", 0.9)"#,
+            ),
+        ];
+        for (function, sql) in cases {
+            let aggregate = aggregate(&format!(
+                r#"{{"function": {}, "column": "price", "name": null}}"#,
+                function
+            ));
+            assert_eq!(aggregate.to_sql(Some(r#""price""#), None).unwrap(), sql);
+        }
+    }
+
+    #[test]
+    fn restricts_to_a_condition() {
+        let aggregate =
+            aggregate(r#"{"function": {"type": "Sum"}, "column": "price", "name": null}"#);
+
+        let sql = aggregate
+            .to_sql(Some(r#""price""#), Some(r#""city" IS NULL"#))
+            .unwrap();
+
+        assert_eq!(sql, r#"SUM("price") FILTER (WHERE "city" IS NULL)"#);
+    }
+
+    #[test]
+    fn only_count_takes_no_column() {
+        let count = aggregate(r#"{"function": {"type": "Count"}, "column": null, "name": null}"#);
+        let sum = aggregate(r#"{"function": {"type": "Sum"}, "column": null, "name": null}"#);
+
+        assert_eq!(count.to_sql(None, None).unwrap(), "COUNT(*)");
+        assert_eq!(
+            sum.to_sql(None, None).unwrap_err().to_string(),
+            "Aggregate `Sum` needs a column"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_percentiles() {
+        for fraction in ["-0.1", "1.5"] {
+            let aggregate = aggregate(&format!(
+                r#"{{"function": {{"type": "Percentile", "fraction": {}}}, "column": "price", "name": null}}"#,
+                fraction
+            ));
+            assert!(aggregate.to_sql(Some(r#""price""#), None).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_functions() {
+        let result = serde_json::from_str::<Aggregate>(
+            r#"{"function": {"type": "StringAgg"}, "column": "name", "name": null}"#,
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn names_results() {
+        let name = |json: &str| aggregate(json).name();
+
+        assert_eq!(
+            name(r#"{"function": {"type": "Count"}, "column": null, "name": null}"#),
+            "count"
+        );
+        assert_eq!(
+            name(r#"{"function": {"type": "CountDistinct"}, "column": "city", "name": null}"#),
+            "count_distinct_city"
+        );
+        assert_eq!(
+            name(
+                r#"{"function": {"type": "Percentile", "fraction": 0.5}, "column": "price", "name": null}"#
+            ),
+            "p50_price"
+        );
+        assert_eq!(
+            name(
+                r#"{"function": {"type": "Percentile", "fraction": 0.07}, "column": "price", "name": null}"#
+            ),
+            "p07_price"
+        );
+        assert_eq!(
+            name(
+                r#"{"function": {"type": "Percentile", "fraction": 0.999}, "column": "price", "name": null}"#
+            ),
+            "p99_9_price"
+        );
+        assert_eq!(
+            name(
+                r#"{"function": {"type": "Percentile", "fraction": 1.0}, "column": "price", "name": null}"#
+            ),
+            "p100_price"
+        );
+        assert_eq!(
+            name(r#"{"function": {"type": "Sum"}, "column": "price", "name": "total"}"#),
+            "total"
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use axum::{
     extract,
     extract::State,
+    http::{header, HeaderMap, StatusCode},
     response::sse::{Event, Sse},
     response::{IntoResponse, Response},
     routing::{get, post},
@@ -20,8 +21,10 @@ use ts_rs::TS;
 struct Assets;
 
 use crate::{
-    config,
+    aggregate::Aggregation,
+    cache, config,
     db::{self, DbBroadcastEvent::Ping, Ordering},
+    filter, graph, lineage, schema, summary,
 };
 
 pub fn new(db: db::DB) -> Router {
@@ -29,6 +32,7 @@ pub fn new(db: db::DB) -> Router {
     Router::new()
         .route("/rpc", post(rpc_handler))
         .route("/sse", get(sse_handler))
+        .route("/query/{name}", get(query_handler))
         .fallback_service(serve_assets)
         .with_state(db)
 }
@@ -38,24 +42,121 @@ async fn rpc_handler(
     extract::Json(request): extract::Json<RpcRequest>,
 ) -> ApiResult<RpcResponse> {
     match request {
-        RpcRequest::ExecQuery(ExecQueryRequest {
-            name,
-            page,
-            page_size,
-            order_by,
-            filters,
-        }) => {
-            let page = page.unwrap_or(1);
-            let page_size = page_size.unwrap_or(100);
-            let ordering = order_by.unwrap_or_default();
-            db.refresh_sources(&name)?;
-            let data = db.exec_query(&name, page, page_size, &ordering, &filters)?;
-            Ok(Json(RpcResponse::ExecQuery(ExecQueryResponse {
-                total_count: data.total_count,
-                data: data.data,
-                schema: serde_json::to_value(data.schema)?,
+        RpcRequest::ExecQuery(request) => Ok(Json(RpcResponse::ExecQuery(
+            exec_query(db, *request, None).await?,
+        ))),
+        RpcRequest::AggregateQuery(request) => {
+            let AggregateQueryRequest { query, aggregation } = *request;
+            Ok(Json(RpcResponse::AggregateQuery(
+                exec_query(db, query, Some(aggregation)).await?,
+            )))
+        }
+        RpcRequest::ListSources => {
+            let sources = db.list_sources()?;
+            Ok(Json(RpcResponse::ListSources(ListSourcesResponse {
+                sources,
+            })))
+        }
+        RpcRequest::DependencyGraph => {
+            let graph = db.dependency_graph()?;
+            let dot = graph.to_dot();
+            Ok(Json(RpcResponse::DependencyGraph(
+                DependencyGraphResponse { graph, dot },
+            )))
+        }
+        RpcRequest::QueryLineage(QueryLineageRequest { name }) => {
+            let columns = tokio::task::spawn_blocking(move || db.query_lineage(&name)).await??;
+            Ok(Json(RpcResponse::QueryLineage(QueryLineageResponse {
+                columns,
+            })))
+        }
+        RpcRequest::DescribeQuery(DescribeQueryRequest { name }) => {
+            let columns = tokio::task::spawn_blocking(move || db.describe_query(&name)).await??;
+            Ok(Json(RpcResponse::DescribeQuery(DescribeQueryResponse {
+                columns,
+            })))
+        }
+        RpcRequest::SummarizeQuery(request) => {
+            let SummarizeQueryRequest {
+                name,
+                filter,
+                raw_filter,
+                params,
+                columns,
+                computed_columns,
+                top_k,
+            } = *request;
+            let columns = run_cancellable(db, name, move |db, name, token| {
+                let options = db::ExecOptions {
+                    filter,
+                    raw_filter,
+                    columns,
+                    computed_columns: computed_columns.unwrap_or_default(),
+                    params: params.unwrap_or_default(),
+                    ..Default::default()
+                };
+                let _leases = db.refresh_sources(name, &options.params, token)?;
+                db.summarize_query(
+                    name,
+                    &options,
+                    top_k.unwrap_or(summary::DEFAULT_TOP_K),
+                    token,
+                )
+            })
+            .await?;
+            Ok(Json(RpcResponse::SummarizeQuery(SummarizeQueryResponse {
+                columns,
+            })))
+        }
+        RpcRequest::ColumnValues(request) => {
+            let ColumnValuesRequest {
+                name,
+                column,
+                filter,
+                raw_filter,
+                params,
+                columns,
+                computed_columns,
+                exclude_column_filter,
+                prefix,
+                limit,
+                buckets,
+            } = *request;
+            let values = run_cancellable(db, name, move |db, name, token| {
+                let options = db::ExecOptions {
+                    filter,
+                    raw_filter,
+                    columns,
+                    computed_columns: computed_columns.unwrap_or_default(),
+                    params: params.unwrap_or_default(),
+                    ..Default::default()
+                };
+                let _leases = db.refresh_sources(name, &options.params, token)?;
+                let values = summary::ValuesOptions {
+                    column,
+                    exclude_column_filter: exclude_column_filter.unwrap_or_default(),
+                    prefix: prefix.filter(|prefix| !prefix.is_empty()),
+                    limit: limit.unwrap_or(summary::DEFAULT_VALUES_LIMIT),
+                    buckets: buckets.unwrap_or(summary::DEFAULT_HISTOGRAM_BUCKETS),
+                };
+                db.column_values(name, &options, &values, token)
+            })
+            .await?;
+            Ok(Json(RpcResponse::ColumnValues(ColumnValuesResponse {
+                values: values.values,
+                has_more: values.has_more,
+                histogram: values.histogram,
             })))
         }
+        RpcRequest::TotalCount(TotalCountRequest { count_token }) => {
+            let total_count = db.total_count(&count_token)?;
+            Ok(Json(RpcResponse::TotalCount(TotalCountResponse {
+                total_count,
+            })))
+        }
+        RpcRequest::CacheStats => Ok(Json(RpcResponse::CacheStats(CacheStatsResponse {
+            stats: db.cache_stats(),
+        }))),
         RpcRequest::ListQueries => {
             let queries = db.list_queries()?;
             Ok(Json(RpcResponse::ListQueries(ListQueriesResponse {
@@ -65,6 +166,194 @@ async fn rpc_handler(
     }
 }
 
+/// Run a query for the `ExecQuery` and `AggregateQuery` RPCs or the
+/// `GET /query/{name}` endpoint.
+async fn exec_query(
+    db: db::DB,
+    request: ExecQueryRequest,
+    aggregation: Option<Aggregation>,
+) -> Result<ExecQueryResponse, AppError> {
+    let ExecQueryRequest {
+        name,
+        page,
+        page_size,
+        order_by,
+        filter,
+        raw_filter,
+        params,
+        keyset,
+        cursor,
+        count,
+        columns,
+        computed_columns,
+        if_none_match,
+    } = request;
+    let page = page.unwrap_or(1);
+    let page_size = page_size.unwrap_or(100);
+    let ordering = order_by.unwrap_or_default();
+    let params = params.unwrap_or_default();
+
+    let (data, etag, warnings) = run_cancellable(db, name, move |db, name, token| {
+        let _leases = db.refresh_sources(name, &params, token)?;
+        let options = db::ExecOptions {
+            page,
+            per_page: page_size,
+            order_by: ordering,
+            filter,
+            raw_filter,
+            keyset: keyset.unwrap_or_default(),
+            cursor,
+            count: count.unwrap_or_default(),
+            columns,
+            computed_columns: computed_columns.unwrap_or_default(),
+            aggregation,
+            params,
+        };
+        let warnings = db.source_warnings(name)?;
+        // Answering "not modified" only takes the ETag, not the query.
+        if let Some(if_none_match) = if_none_match {
+            let etag = db.result_etag(name, &options, token)?;
+            if etag_matches(&if_none_match, &etag) {
+                return anyhow::Ok((None, etag, warnings));
+            }
+        }
+        let data = db.exec_query(name, &options, token)?;
+        let etag = data.etag.clone();
+        anyhow::Ok((Some(data), etag, warnings))
+    })
+    .await?;
+    Ok(match data {
+        Some(data) => ExecQueryResponse {
+            total_count: data.total_count,
+            total_count_estimated: data.total_count_estimated,
+            count_token: data.count_token,
+            data: data.data,
+            schema: data.schema,
+            next_cursor: data.next_cursor,
+            prev_cursor: data.prev_cursor,
+            warnings,
+            etag,
+            not_modified: false,
+            from_cache: data.from_cache,
+        },
+        None => ExecQueryResponse {
+            total_count: None,
+            total_count_estimated: false,
+            count_token: None,
+            data: vec![],
+            schema: vec![],
+            next_cursor: None,
+            prev_cursor: None,
+            warnings,
+            etag,
+            not_modified: true,
+            from_cache: false,
+        },
+    })
+}
+
+/// Run `run` for the query `name` on a blocking thread, cancelling it through
+/// the token it is given if it outlives the query's timeout or the request
+/// is dropped.
+async fn run_cancellable<T: Send + 'static>(
+    db: db::DB,
+    name: String,
+    run: impl FnOnce(&db::DB, &str, &db::CancelToken) -> anyhow::Result<T> + Send + 'static,
+) -> Result<T, AppError> {
+    let mut guard = CancelOnDrop {
+        token: Default::default(),
+        name: name.clone(),
+        finished: false,
+    };
+    let timeout = db.query_timeout(&name);
+    let task = tokio::task::spawn_blocking({
+        let name = name.clone();
+        let token = guard.token.clone();
+        move || run(&db, &name, &token)
+    });
+    let result = match timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, task).await {
+            Ok(result) => result,
+            Err(_) => {
+                eprintln!(
+                    "Query `{}` timed out after {}s, cancelling it",
+                    name,
+                    timeout.as_secs()
+                );
+                guard.token.cancel();
+                guard.finished = true;
+                return Err(anyhow::anyhow!(
+                    "Query `{}` timed out after {}s",
+                    name,
+                    timeout.as_secs()
+                )
+                .into());
+            }
+        },
+        None => task.await,
+    };
+    guard.finished = true;
+    Ok(result??)
+}
+
+/// Query parameters of `GET /query/{name}`.
+#[derive(Deserialize)]
+struct QueryParams {
+    page: Option<u32>,
+    page_size: Option<u32>,
+    keyset: Option<bool>,
+    cursor: Option<String>,
+    count: Option<db::CountMode>,
+}
+
+/// A page of a query's results, answering `304 Not Modified` when the
+/// `If-None-Match` header lists its current ETag.
+async fn query_handler(
+    State(db): State<db::DB>,
+    extract::Path(name): extract::Path<String>,
+    extract::Query(query): extract::Query<QueryParams>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let if_none_match = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let response = exec_query(
+        db,
+        ExecQueryRequest {
+            name,
+            page: query.page,
+            page_size: query.page_size,
+            order_by: None,
+            filter: None,
+            raw_filter: None,
+            params: None,
+            keyset: query.keyset,
+            cursor: query.cursor,
+            count: query.count,
+            columns: None,
+            computed_columns: None,
+            if_none_match,
+        },
+        None,
+    )
+    .await?;
+    let etag = [(header::ETAG, response.etag.clone())];
+    if response.not_modified {
+        return Ok((StatusCode::NOT_MODIFIED, etag).into_response());
+    }
+    Ok((etag, Json(response)).into_response())
+}
+
+/// Whether an `If-None-Match` value lists `etag`. Weak comparison, as HTTP
+/// requires for `If-None-Match`.
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match
+        .split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
 async fn sse_handler(
     State(db): State<db::DB>,
 ) -> Sse<impl Stream<Item = Result<Event, anyhow::Error>>> {
@@ -90,25 +379,190 @@ async fn sse_handler(
 
 type ApiResult<T> = Result<Json<T>, AppError>;
 
+/// Cancels a query that is still running when the request handling it is
+/// dropped, which is what happens when the client disconnects.
+struct CancelOnDrop {
+    token: std::sync::Arc<db::CancelToken>,
+    name: String,
+    finished: bool,
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if !self.finished {
+            eprintln!(
+                "Request for query `{}` was dropped, cancelling it",
+                self.name
+            );
+            self.token.cancel();
+        }
+    }
+}
+
 #[derive(TS, Serialize, Deserialize)]
 struct ListQueriesResponse {
     queries: Vec<config::Query>,
 }
 
+#[derive(TS, Serialize, Deserialize)]
+struct ListSourcesResponse {
+    sources: Vec<db::SourceStatus>,
+}
+
+#[derive(TS, Serialize, Deserialize)]
+struct DependencyGraphResponse {
+    graph: graph::Graph,
+    /// The same graph in Graphviz DOT format.
+    dot: String,
+}
+
+#[derive(TS, Serialize, Deserialize)]
+struct QueryLineageRequest {
+    name: String,
+}
+
+#[derive(TS, Serialize, Deserialize)]
+struct QueryLineageResponse {
+    columns: Vec<lineage::ColumnLineage>,
+}
+
+#[derive(TS, Serialize, Deserialize)]
+struct DescribeQueryRequest {
+    name: String,
+}
+
+#[derive(TS, Serialize, Deserialize)]
+struct DescribeQueryResponse {
+    columns: Vec<schema::ColumnSchema>,
+}
+
+#[derive(TS, Serialize, Deserialize)]
+struct SummarizeQueryRequest {
+    name: String,
+    /// Only the rows matching the filters are summarized, as in
+    /// `ExecQuery`.
+    filter: Option<filter::Filter>,
+    raw_filter: Option<String>,
+    params: Option<config::Params>,
+    columns: Option<Vec<String>>,
+    computed_columns: Option<Vec<db::ComputedColumn>>,
+    /// How many of the most frequent values to return for each column.
+    /// Defaults to 5.
+    top_k: Option<u32>,
+}
+
+#[derive(TS, Serialize, Deserialize)]
+struct SummarizeQueryResponse {
+    columns: Vec<summary::ColumnSummary>,
+}
+
+#[derive(TS, Serialize, Deserialize)]
+struct ColumnValuesRequest {
+    name: String,
+    /// A column, or a dotted path into a struct column as in `Ordering`.
+    column: String,
+    filter: Option<filter::Filter>,
+    raw_filter: Option<String>,
+    params: Option<config::Params>,
+    columns: Option<Vec<String>>,
+    computed_columns: Option<Vec<db::ComputedColumn>>,
+    /// Leave out the conditions of `filter` on the column itself, so that
+    /// the values are those the filter could be changed to select.
+    exclude_column_filter: Option<bool>,
+    /// Only return values whose text starts with this, ignoring case.
+    prefix: Option<String>,
+    /// How many values to return at most. Defaults to 100.
+    limit: Option<u32>,
+    /// How many histogram buckets to return for a numeric or temporal
+    /// column. Defaults to 10; 0 for none.
+    buckets: Option<u32>,
+}
+
+#[derive(TS, Serialize, Deserialize)]
+struct ColumnValuesResponse {
+    values: Vec<summary::ValueCount>,
+    has_more: bool,
+    histogram: Option<Vec<summary::HistogramBucket>>,
+}
+
+#[derive(TS, Serialize, Deserialize)]
+struct TotalCountRequest {
+    count_token: String,
+}
+
+#[derive(TS, Serialize, Deserialize)]
+struct TotalCountResponse {
+    /// `None` while the rows are still being counted.
+    total_count: Option<u32>,
+}
+
+#[derive(TS, Serialize, Deserialize)]
+struct CacheStatsResponse {
+    stats: cache::CacheStats,
+}
+
+/// Groups of the rows of a query, paged through like the rows are by
+/// `ExecQuery`.
+#[derive(TS, Serialize, Deserialize)]
+struct AggregateQueryRequest {
+    #[serde(flatten)]
+    query: ExecQueryRequest,
+    #[serde(flatten)]
+    aggregation: Aggregation,
+}
+
 #[derive(TS, Serialize, Deserialize)]
 struct ExecQueryRequest {
     name: String,
     page: Option<u32>,
     page_size: Option<u32>,
     order_by: Option<Vec<Ordering>>,
-    filters: String,
+    filter: Option<filter::Filter>,
+    /// A SQL boolean expression, only accepted when the server runs with
+    /// `--allow-raw-filters`.
+    raw_filter: Option<String>,
+    params: Option<config::Params>,
+    /// Page by cursor instead of by page number, with the ordering made
+    /// unique by the query's key columns. `page` is then ignored.
+    keyset: Option<bool>,
+    /// The `next_cursor` or `prev_cursor` of a previous response, to fetch
+    /// the page after or before it. Implies `keyset`.
+    cursor: Option<String>,
+    /// How to count the rows matching the filters. Defaults to `Exact`.
+    count: Option<db::CountMode>,
+    /// The columns to return, in order. All of them by default.
+    columns: Option<Vec<String>>,
+    /// Columns computed from the others, added after them. Only accepted
+    /// when the server runs with `--allow-computed-columns`.
+    computed_columns: Option<Vec<db::ComputedColumn>>,
+    /// The ETag of a previous response. If the results have not changed
+    /// since, the response is `not_modified` and carries no data.
+    if_none_match: Option<String>,
 }
 
 #[derive(TS, Serialize, Deserialize)]
 struct ExecQueryResponse {
-    total_count: u32,
+    /// `None` in `Deferred` count mode until the count is known.
+    total_count: Option<u32>,
+    total_count_estimated: bool,
+    /// In `Deferred` count mode, identifies the count for the `TotalCount`
+    /// RPC and event.
+    count_token: Option<String>,
     data: Vec<Vec<serde_json::Value>>,
-    schema: serde_json::Value,
+    /// The name and type of each column of `data`.
+    schema: Vec<schema::Field>,
+    /// In keyset mode, the cursors of the next and previous pages, if there
+    /// are any.
+    next_cursor: Option<String>,
+    prev_cursor: Option<String>,
+    warnings: Vec<String>,
+    /// Changes whenever the results may have changed.
+    etag: String,
+    /// Whether the results still match the request's `if_none_match`, in
+    /// which case `total_count`, `data` and `schema` are left empty.
+    not_modified: bool,
+    /// Whether the page was served from the result cache.
+    from_cache: bool,
 }
 
 // Make our own error that wraps `anyhow::Error`.
@@ -117,11 +571,7 @@ struct AppError(anyhow::Error);
 // Tell axum how to convert `AppError` into a response.
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        (
-            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-            format!("{}", self.0),
-        )
-            .into_response()
+        (StatusCode::INTERNAL_SERVER_ERROR, format!("{}", self.0)).into_response()
     }
 }
 
@@ -136,15 +586,21 @@ where
     }
 }
 
-#[derive(TS, Serialize, Deserialize)]
-pub struct ListQueriesRequest {}
-
 #[derive(TS, Serialize, Deserialize)]
 #[serde(tag = "rpcType")]
 #[ts(export)]
 enum RpcRequest {
     ListQueries,
-    ExecQuery(ExecQueryRequest),
+    ListSources,
+    DependencyGraph,
+    QueryLineage(QueryLineageRequest),
+    DescribeQuery(DescribeQueryRequest),
+    SummarizeQuery(Box<SummarizeQueryRequest>),
+    ColumnValues(Box<ColumnValuesRequest>),
+    TotalCount(TotalCountRequest),
+    CacheStats,
+    ExecQuery(Box<ExecQueryRequest>),
+    AggregateQuery(Box<AggregateQueryRequest>),
 }
 
 #[derive(TS, Serialize)]
@@ -152,5 +608,14 @@ enum RpcRequest {
 #[ts(export)]
 enum RpcResponse {
     ListQueries(ListQueriesResponse),
+    ListSources(ListSourcesResponse),
+    DependencyGraph(DependencyGraphResponse),
+    QueryLineage(QueryLineageResponse),
+    DescribeQuery(DescribeQueryResponse),
+    SummarizeQuery(SummarizeQueryResponse),
+    ColumnValues(ColumnValuesResponse),
+    TotalCount(TotalCountResponse),
+    CacheStats(CacheStatsResponse),
     ExecQuery(ExecQueryResponse),
+    AggregateQuery(ExecQueryResponse),
 }
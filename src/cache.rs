@@ -0,0 +1,170 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::config::Params;
+use crate::db::ExecQueryResult;
+
+pub const DEFAULT_CACHE_SIZE_MB: usize = 64;
+
+#[derive(TS, Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub entries: u64,
+    pub bytes: u64,
+    pub max_bytes: u64,
+}
+
+struct Entry {
+    query: String,
+    params: Params,
+    version: u64,
+    result: ExecQueryResult,
+    bytes: usize,
+    last_used: u64,
+}
+
+/// Pages of query results, evicting the least recently used once they take
+/// up more than `max_bytes`.
+///
+/// Entries are looked up by a key describing the request (query, page,
+/// ordering, filters...) and only returned if they were computed from the
+/// same version of the query's SQL and data.
+pub struct ResultCache {
+    max_bytes: usize,
+    entries: HashMap<u64, Entry>,
+    /// Keys by the tick they were last used at, oldest first.
+    recency: BTreeMap<u64, u64>,
+    tick: u64,
+    stats: CacheStats,
+}
+
+impl ResultCache {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            tick: 0,
+            stats: CacheStats {
+                max_bytes: max_bytes as u64,
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn get(&mut self, key: u64, version: u64) -> Option<ExecQueryResult> {
+        self.tick += 1;
+        match self.entries.get_mut(&key) {
+            Some(entry) if entry.version == version => {
+                self.recency.remove(&entry.last_used);
+                entry.last_used = self.tick;
+                self.recency.insert(self.tick, key);
+                self.stats.hits += 1;
+                Some(entry.result.clone())
+            }
+            _ => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub fn insert(
+        &mut self,
+        key: u64,
+        query: &str,
+        params: &Params,
+        version: u64,
+        result: ExecQueryResult,
+    ) {
+        // Results of an older version can never be hit again. Other
+        // parameters have versions of their own.
+        let stale = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| {
+                entry.query == query && entry.params == *params && entry.version != version
+            })
+            .map(|(key, _)| *key)
+            .collect::<Vec<_>>();
+        for key in stale {
+            self.remove(key);
+        }
+        self.remove(key);
+
+        let bytes = result_size(&result);
+        if bytes > self.max_bytes {
+            return;
+        }
+        while self.stats.bytes as usize + bytes > self.max_bytes {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            self.remove(oldest);
+            self.stats.evictions += 1;
+        }
+
+        self.tick += 1;
+        self.recency.insert(self.tick, key);
+        self.entries.insert(
+            key,
+            Entry {
+                query: query.to_string(),
+                params: params.clone(),
+                version,
+                result,
+                bytes,
+                last_used: self.tick,
+            },
+        );
+        self.stats.bytes += bytes as u64;
+        self.stats.entries += 1;
+    }
+
+    fn remove(&mut self, key: u64) {
+        if let Some(entry) = self.entries.remove(&key) {
+            self.recency.remove(&entry.last_used);
+            self.stats.bytes -= entry.bytes as u64;
+            self.stats.entries -= 1;
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats.clone()
+    }
+}
+
+/// Roughly how much memory a result takes, going by its JSON encoding.
+fn result_size(result: &ExecQueryResult) -> usize {
+    serde_json::to_vec(&result.data)
+        .map(|data| data.len())
+        .unwrap_or(0)
+        + result.schema.len() * 64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_new_version_only_evicts_results_with_the_same_params() {
+        let mut cache = ResultCache::new(1024 * 1024);
+        let day = |value: &str| Params::from([("day".to_string(), value.to_string())]);
+        cache.insert(1, "logs", &day("1"), 10, ExecQueryResult::default());
+        cache.insert(2, "logs", &day("1"), 10, ExecQueryResult::default());
+        cache.insert(3, "logs", &day("2"), 20, ExecQueryResult::default());
+
+        // The data for day 1 changed: its other pages are stale, but day 2's
+        // are not.
+        cache.insert(1, "logs", &day("1"), 11, ExecQueryResult::default());
+
+        assert!(cache.get(1, 11).is_some());
+        assert!(cache.get(2, 10).is_none());
+        assert!(cache.get(3, 20).is_some());
+        assert_eq!(cache.stats().entries, 2);
+    }
+}
@@ -1,5 +1,5 @@
 use clap::{Args, Parser};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use crate::config;
 
@@ -14,6 +14,103 @@ pub struct CliConfig {
 
     #[arg(long, required = false)]
     pub open: bool,
+
+    /// Allow clients to filter query results with raw SQL expressions.
+    #[arg(long)]
+    pub allow_raw_filters: bool,
+
+    /// Allow clients to add columns computed with SQL expressions.
+    #[arg(long)]
+    pub allow_computed_columns: bool,
+
+    /// Timeout in seconds for a `--sql` or `--sql-file` query.
+    #[arg(long)]
+    pub query_timeout: Option<u64>,
+
+    /// Column identifying the rows of a `--sql` or `--sql-file` query, for
+    /// paging by cursor. May be repeated for a key of several columns.
+    #[arg(long = "key-column")]
+    pub key_columns: Vec<String>,
+
+    /// Timeout in seconds for every query without its own `--query-timeout`.
+    #[arg(long)]
+    pub default_query_timeout: Option<u64>,
+
+    /// Memory in megabytes for caching query results. 0 disables the cache.
+    #[arg(long)]
+    pub cache_size_mb: Option<usize>,
+
+    /// Extra request header for `--url` sources, as `Name: value`. May be
+    /// repeated.
+    #[arg(long = "header", requires = "url", value_parser = parse_header)]
+    pub headers: Vec<(String, String)>,
+
+    /// Environment variable holding a bearer token for `--url` sources.
+    #[arg(long, requires = "url")]
+    pub bearer_token_env: Option<String>,
+
+    /// Request timeout in seconds for `--url` sources.
+    #[arg(long, requires = "url")]
+    pub timeout: Option<u64>,
+
+    /// Format of a `--url` source. Guessed from the URL when omitted.
+    #[arg(long, value_enum, requires = "url")]
+    pub format: Option<CliDataFormat>,
+
+    /// Regex applied to each line of a `--text-file` or `--text-cmd` source.
+    /// Named capture groups become columns.
+    #[arg(long)]
+    pub pattern: Option<String>,
+
+    /// Query parameter taken by a `--json-cmd` or `--text-cmd` source. Its
+    /// value is passed to the command as an environment variable of the same
+    /// name. May be repeated.
+    #[arg(long = "param")]
+    pub params: Vec<String>,
+
+    /// Type override for a column of a source, as `name:TYPE`. May be
+    /// repeated.
+    #[arg(long = "column", value_parser = parse_column)]
+    pub columns: Vec<(String, String)>,
+
+    /// Rename a column of a source, as `old:new`. May be repeated.
+    #[arg(long = "rename", value_parser = parse_rename)]
+    pub renames: Vec<(String, String)>,
+
+    /// Fail refreshing a source when its schema drifts.
+    #[arg(long)]
+    pub strict_schema: bool,
+}
+
+/// `frogtable graph [--format dot] -- <config>...` prints the dependency graph
+/// of the configured sources, initializers and queries, then exits.
+#[derive(Parser, Debug, Clone)]
+pub struct GraphCommand {
+    #[arg(long, value_enum, default_value = "json")]
+    pub format: GraphFormat,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+pub enum GraphFormat {
+    Json,
+    Dot,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+pub enum CliDataFormat {
+    Json,
+    Csv,
+    Parquet,
+}
+
+impl From<CliDataFormat> for config::DataFormat {
+    fn from(format: CliDataFormat) -> Self {
+        match format {
+            CliDataFormat::Json => config::DataFormat::Json,
+            CliDataFormat::Csv => config::DataFormat::Csv,
+            CliDataFormat::Parquet => config::DataFormat::Parquet,
+        }
+    }
 }
 
 #[derive(Args, Debug, Clone)]
@@ -29,68 +126,178 @@ pub struct CliSource {
     pub sql: Option<String>,
     #[arg(long)]
     pub setup_sql: Option<String>,
+    #[arg(long, requires = "name")]
+    pub url: Option<String>,
+    #[arg(long, requires = "pattern")]
+    pub text_file: Option<PathBuf>,
+    #[arg(long, requires_all = ["name", "pattern"])]
+    pub text_cmd: Option<String>,
+    #[arg(long, requires = "name")]
+    pub multi_json_cmd: Option<String>,
 }
 
-fn name_from_path(path: &PathBuf) -> Option<String> {
+fn name_from_path(path: &Path) -> Option<String> {
     path.file_stem().map(|s| s.to_string_lossy().to_string())
 }
 
+/// Split `arg` at its first colon, for flags given as e.g. `name:TYPE`.
+fn parse_pair(arg: &str, expected: &str) -> Result<(String, String), String> {
+    match arg.split_once(':') {
+        Some((name, value)) => Ok((name.trim().to_string(), value.trim().to_string())),
+        None => Err(format!("expected `{}`, got `{}`", expected, arg)),
+    }
+}
+
+fn parse_header(arg: &str) -> Result<(String, String), String> {
+    parse_pair(arg, "Name: value")
+}
+
+fn parse_column(arg: &str) -> Result<(String, String), String> {
+    parse_pair(arg, "name:TYPE")
+}
+
+fn parse_rename(arg: &str) -> Result<(String, String), String> {
+    parse_pair(arg, "old:new")
+}
+
+fn column_override(
+    columns: &mut Vec<config::ColumnOverride>,
+    name: String,
+) -> &mut config::ColumnOverride {
+    match columns.iter().position(|column| column.name == name) {
+        Some(idx) => &mut columns[idx],
+        None => {
+            columns.push(config::ColumnOverride {
+                name,
+                data_type: None,
+                rename: None,
+            });
+            columns.last_mut().unwrap()
+        }
+    }
+}
+
 impl CliConfig {
+    fn source_schema(&self) -> config::SourceSchema {
+        let mut columns: Vec<config::ColumnOverride> = vec![];
+        for (name, data_type) in self.columns.iter() {
+            column_override(&mut columns, name.clone()).data_type = Some(data_type.clone());
+        }
+        for (name, new_name) in self.renames.iter() {
+            column_override(&mut columns, name.clone()).rename = Some(new_name.clone());
+        }
+
+        config::SourceSchema {
+            columns,
+            strict: self.strict_schema,
+        }
+    }
+
     pub fn append_to_root_config(&self, root_config: &mut config::RootConfig) {
         if self.open {
             root_config.open = true;
         }
+        if self.allow_raw_filters {
+            root_config.allow_raw_filters = true;
+        }
+        if self.allow_computed_columns {
+            root_config.allow_computed_columns = true;
+        }
+        if let Some(timeout) = self.default_query_timeout {
+            root_config.query_timeout_secs = Some(timeout);
+        }
+        if let Some(size) = self.cache_size_mb {
+            root_config.cache_size_mb = size;
+        }
 
         match &self.source {
             CliSource {
                 json_file: Some(path),
-                json_cmd: None,
-                sql_file: None,
-                sql: None,
-                setup_sql: None,
+                ..
             } => root_config.sources.push(config::Data {
                 name: self.name.clone().or(name_from_path(path)).unwrap(),
                 source: config::DataSource::JsonFile(path.clone()),
+                params: vec![],
+                schema: self.source_schema(),
             }),
             CliSource {
-                json_file: None,
                 json_cmd: Some(command),
-                sql_file: None,
-                sql: None,
-                setup_sql: None,
+                ..
             } => root_config.sources.push(config::Data {
                 name: self.name.clone().unwrap(),
                 source: config::DataSource::JsonCmd(command.clone()),
+                params: self.params.clone(),
+                schema: self.source_schema(),
             }),
             CliSource {
-                json_file: None,
-                json_cmd: None,
                 sql_file: Some(path),
-                sql: None,
-                setup_sql: None,
+                ..
             } => root_config.queries.push(config::Query {
                 name: self.name.clone().or(name_from_path(path)).unwrap(),
                 source: config::QuerySource::SqlFile(path.clone()),
+                timeout_secs: self.query_timeout,
+                key_columns: self.key_columns.clone(),
             }),
-            CliSource {
-                json_file: None,
-                json_cmd: None,
-                sql_file: None,
-                sql: Some(sql),
-                setup_sql: None,
-            } => root_config.queries.push(config::Query {
+            CliSource { sql: Some(sql), .. } => root_config.queries.push(config::Query {
                 name: self.name.clone().unwrap(),
                 source: config::QuerySource::SqlString(sql.clone()),
+                timeout_secs: self.query_timeout,
+                key_columns: self.key_columns.clone(),
             }),
             CliSource {
-                json_file: None,
-                json_cmd: None,
-                sql_file: None,
-                sql: None,
                 setup_sql: Some(sql),
+                ..
             } => root_config.initializers.push(config::Initializer {
                 source: config::QuerySource::SqlString(sql.clone()),
             }),
+            CliSource { url: Some(url), .. } => root_config.sources.push(config::Data {
+                name: self.name.clone().unwrap(),
+                source: config::DataSource::Url(config::UrlSource {
+                    url: url.clone(),
+                    format: self
+                        .format
+                        .map(config::DataFormat::from)
+                        .unwrap_or_else(|| config::DataFormat::from_extension(url)),
+                    headers: self.headers.clone(),
+                    bearer_token_env: self.bearer_token_env.clone(),
+                    timeout_secs: self.timeout,
+                }),
+                params: vec![],
+                schema: self.source_schema(),
+            }),
+            CliSource {
+                text_file: Some(path),
+                ..
+            } => root_config.sources.push(config::Data {
+                name: self.name.clone().or(name_from_path(path)).unwrap(),
+                source: config::DataSource::TextFile {
+                    path: path.clone(),
+                    pattern: self.pattern.clone().unwrap(),
+                },
+                params: vec![],
+                schema: self.source_schema(),
+            }),
+            CliSource {
+                text_cmd: Some(command),
+                ..
+            } => root_config.sources.push(config::Data {
+                name: self.name.clone().unwrap(),
+                source: config::DataSource::TextCmd {
+                    cmd: command.clone(),
+                    pattern: self.pattern.clone().unwrap(),
+                },
+                params: self.params.clone(),
+                schema: self.source_schema(),
+            }),
+            CliSource {
+                multi_json_cmd: Some(command),
+                ..
+            } => root_config.sources.push(config::Data {
+                name: self.name.clone().unwrap(),
+                source: config::DataSource::MultiJsonCmd(command.clone()),
+                params: vec![],
+                schema: self.source_schema(),
+            }),
             _ => panic!("Invalid config"),
         }
     }
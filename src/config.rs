@@ -1,19 +1,39 @@
 use std::{
-    io::{self, BufRead, Write},
-    path::PathBuf,
+    collections::BTreeMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    io::{self, BufRead},
+    path::{Path, PathBuf},
 };
 
 use serde::{Deserialize, Serialize};
 
+use crate::http;
+use crate::text;
+
 static SCRATCH_DIR: std::sync::LazyLock<PathBuf> =
     std::sync::LazyLock::new(|| dirs::cache_dir().unwrap().join("frogtable").join("debug"));
 
+/// How many parameter combinations of a parameterized source are kept in the
+/// scratch dir before the least recently refreshed ones are evicted.
+const MAX_CACHED_PARAM_SETS: usize = 32;
+
+/// Parameter values supplied with a query execution, by parameter name.
+pub type Params = BTreeMap<String, String>;
+
 #[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
 pub struct RootConfig {
     pub initializers: Vec<Initializer>,
     pub sources: Vec<Data>,
     pub queries: Vec<Query>,
     pub open: bool,
+    /// Accept raw SQL in `ExecQueryRequest.raw_filter`.
+    pub allow_raw_filters: bool,
+    /// Accept `ExecQueryRequest.computed_columns`.
+    pub allow_computed_columns: bool,
+    /// Timeout for queries that do not set their own.
+    pub query_timeout_secs: Option<u64>,
+    /// Memory for caching query results.
+    pub cache_size_mb: usize,
 }
 
 impl RootConfig {
@@ -23,6 +43,10 @@ impl RootConfig {
             sources: vec![],
             queries: vec![],
             open: false,
+            allow_raw_filters: false,
+            allow_computed_columns: false,
+            query_timeout_secs: None,
+            cache_size_mb: crate::cache::DEFAULT_CACHE_SIZE_MB,
         }
     }
 }
@@ -37,73 +61,440 @@ pub struct Initializer {
 pub struct Data {
     pub name: String,
     pub source: DataSource,
+    /// Names of the query parameters a command source takes. Their values are
+    /// passed to the command as environment variables, and each distinct
+    /// combination is cached separately.
+    pub params: Vec<String>,
+    pub schema: SourceSchema,
+}
+
+/// Declared columns of a source, overriding what DuckDB infers.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ts_rs::TS)]
+pub struct SourceSchema {
+    pub columns: Vec<ColumnOverride>,
+    /// Fail the refresh when a declared column is missing or when the
+    /// inferred schema differs from the previous refresh, instead of only
+    /// broadcasting a `SchemaChanged` event.
+    pub strict: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
+pub struct ColumnOverride {
+    pub name: String,
+    /// DuckDB type the column is cast to.
+    pub data_type: Option<String>,
+    pub rename: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
 pub enum DataSource {
     JsonFile(PathBuf),
     JsonCmd(String),
+    Url(UrlSource),
+    /// A plain-text file, split into lines and parsed with a regex.
+    TextFile {
+        path: PathBuf,
+        pattern: String,
+    },
+    /// A command whose stdout is split into lines and parsed with a regex.
+    TextCmd {
+        cmd: String,
+        pattern: String,
+    },
+    /// A command producing several tables at once, either as a JSON object
+    /// of named arrays on stdout, or as files written to the directory in
+    /// `$FROGTABLE_OUTPUT_DIR`. Each table is registered as its own view.
+    MultiJsonCmd(String),
+}
+
+/// What a refresh found besides the data itself, for the source's status.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RefreshReport {
+    /// Lines of a text source that did not match its pattern.
+    pub unmatched_lines: u64,
+}
+
+/// What the parsed file of a text source was produced from, stored next to it
+/// so that the text is only parsed again when it or the pattern changed.
+#[derive(Debug, Serialize, Deserialize)]
+struct ParsedFrom {
+    path: PathBuf,
+    pattern: String,
+    modified: Option<std::time::SystemTime>,
+    len: u64,
+    unmatched_lines: u64,
+}
+
+impl ParsedFrom {
+    fn same_input(&self, other: &ParsedFrom) -> bool {
+        self.path == other.path
+            && self.pattern == other.pattern
+            && self.modified.is_some()
+            && self.modified == other.modified
+            && self.len == other.len
+    }
+}
+
+/// A single table produced by a source.
+#[derive(Debug, Clone)]
+pub struct SourceTable {
+    pub name: String,
+    pub path: PathBuf,
+    pub format: DataFormat,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
+pub struct UrlSource {
+    pub url: String,
+    pub format: DataFormat,
+    pub headers: Vec<(String, String)>,
+    /// Name of an environment variable holding a bearer token. It is read on
+    /// every refresh so that rotated tokens are picked up.
+    pub bearer_token_env: Option<String>,
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ts_rs::TS)]
+pub enum DataFormat {
+    Json,
+    Csv,
+    Parquet,
+}
+
+impl DataFormat {
+    /// Guess the format from the extension of a path or URL, defaulting to
+    /// JSON.
+    pub fn from_extension(path: &str) -> Self {
+        let path = path.split(['?', '#']).next().unwrap_or(path);
+        match path.rsplit('.').next().map(|ext| ext.to_lowercase()) {
+            Some(ext) if ext == "csv" || ext == "tsv" => DataFormat::Csv,
+            Some(ext) if ext == "parquet" => DataFormat::Parquet,
+            _ => DataFormat::Json,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            DataFormat::Json => "json",
+            DataFormat::Csv => "csv",
+            DataFormat::Parquet => "parquet",
+        }
+    }
 }
 
 impl Data {
+    pub fn format(&self) -> DataFormat {
+        match &self.source {
+            DataSource::JsonFile(_) => DataFormat::Json,
+            DataSource::JsonCmd(_) => DataFormat::Json,
+            DataSource::Url(url_source) => url_source.format,
+            DataSource::TextFile { .. } => DataFormat::Json,
+            DataSource::TextCmd { .. } => DataFormat::Json,
+            DataSource::MultiJsonCmd(_) => DataFormat::Json,
+        }
+    }
+
     fn out_path_extension(&self) -> String {
+        self.format().extension().to_owned()
+    }
+
+    fn out_path(&self, params: &Params) -> anyhow::Result<PathBuf> {
+        if self.params.is_empty() {
+            return Ok(SCRATCH_DIR
+                .join("sources")
+                .join(&self.name)
+                .with_extension(self.out_path_extension()));
+        }
+
+        Ok(self
+            .params_cache_dir()
+            .join(self.params_key(params)?)
+            .with_extension(self.out_path_extension()))
+    }
+
+    pub fn path(&self, params: &Params) -> anyhow::Result<PathBuf> {
         match &self.source {
-            DataSource::JsonFile(_) => "json",
-            DataSource::JsonCmd(_) => "json",
+            DataSource::JsonFile(path) => Ok(path.clone()),
+            DataSource::JsonCmd(_) => self.out_path(params),
+            DataSource::Url(_) => self.out_path(params),
+            DataSource::TextFile { .. } => self.out_path(params),
+            DataSource::TextCmd { .. } => self.out_path(params),
+            DataSource::MultiJsonCmd(_) => Ok(self.out_dir()),
         }
-        .to_owned()
     }
 
-    fn out_path(&self) -> PathBuf {
+    fn params_cache_dir(&self) -> PathBuf {
         SCRATCH_DIR
             .join("sources")
-            .join(&self.name)
-            .with_extension(self.out_path_extension())
+            .join(format!("{}.params", self.name))
     }
 
-    pub fn path(&self) -> PathBuf {
-        match &self.source {
-            DataSource::JsonFile(path) => path.clone(),
-            DataSource::JsonCmd(_) => self.out_path(),
+    /// The values of the parameters this source takes, in declaration order.
+    fn param_values<'a>(&'a self, params: &'a Params) -> anyhow::Result<Vec<(&'a str, &'a str)>> {
+        self.params
+            .iter()
+            .map(|name| {
+                params
+                    .get(name)
+                    .map(|value| (name.as_str(), value.as_str()))
+                    .ok_or(anyhow::anyhow!(
+                        "Source `{}` requires the parameter `{}`",
+                        self.name,
+                        name
+                    ))
+            })
+            .collect()
+    }
+
+    /// A file name identifying one combination of parameter values.
+    pub fn params_key(&self, params: &Params) -> anyhow::Result<String> {
+        let mut hasher = DefaultHasher::new();
+        self.param_values(params)?.hash(&mut hasher);
+        Ok(format!("{:016x}", hasher.finish()))
+    }
+
+    /// Remove all but the most recently refreshed parameter combinations.
+    fn evict_cached_params(&self) -> anyhow::Result<()> {
+        let mut entries = std::fs::read_dir(self.params_cache_dir())?
+            .map(|entry| {
+                let entry = entry?;
+                Ok((entry.metadata()?.modified()?, entry.path()))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+        for (_, path) in entries.iter().skip(MAX_CACHED_PARAM_SETS) {
+            std::fs::remove_file(path)?;
         }
+        Ok(())
     }
 
-    pub fn refresh(&self) -> anyhow::Result<()> {
-        if let DataSource::JsonCmd(cmd) = &self.source {
-            let out_path = self.out_path();
-            std::fs::create_dir_all(out_path.parent().unwrap())?;
-            let output = std::process::Command::new("bash")
-                .arg("-c")
-                .arg(cmd.clone())
-                .output()?;
+    fn out_dir(&self) -> PathBuf {
+        SCRATCH_DIR.join("sources").join(&self.name)
+    }
 
-            output.stderr.lines().for_each(|line| {
-                eprintln!(
-                    "[Refreshing {}]: {}",
-                    self.name,
-                    line.unwrap_or_else(|_| "".to_string())
-                );
-            });
+    /// The tables this source provides. Most sources provide a single table
+    /// named after the source; `MultiJsonCmd` provides one table per file in
+    /// its output directory, so this reflects the most recent refresh.
+    pub fn tables(&self, params: &Params) -> anyhow::Result<Vec<SourceTable>> {
+        if let DataSource::MultiJsonCmd(_) = &self.source {
+            let mut tables = vec![];
+            if !self.out_dir().exists() {
+                return Ok(tables);
+            }
+            for entry in std::fs::read_dir(self.out_dir())? {
+                let path = entry?.path();
+                let (Some(stem), Some(extension)) = (path.file_stem(), path.extension()) else {
+                    continue;
+                };
+                tables.push(SourceTable {
+                    name: stem.to_string_lossy().to_string(),
+                    format: DataFormat::from_extension(&extension.to_string_lossy()),
+                    path,
+                });
+            }
+            tables.sort_by(|a, b| a.name.cmp(&b.name));
+            return Ok(tables);
+        }
+
+        Ok(vec![SourceTable {
+            name: self.name.clone(),
+            path: self.path(params)?,
+            format: self.format(),
+        }])
+    }
+
+    /// The names of the tables this source provides, which unlike `tables`
+    /// does not depend on parameter values.
+    pub fn table_names(&self) -> anyhow::Result<Vec<String>> {
+        if let DataSource::MultiJsonCmd(_) = &self.source {
+            return Ok(self
+                .tables(&Params::new())?
+                .into_iter()
+                .map(|table| table.name)
+                .collect());
+        }
+        Ok(vec![self.name.clone()])
+    }
+
+    pub fn refresh(&self, params: &Params) -> anyhow::Result<RefreshReport> {
+        let out_path = self.out_path(params)?;
+        let mut report = RefreshReport::default();
+        match &self.source {
+            DataSource::JsonFile(_) => {}
+            DataSource::JsonCmd(cmd) => {
+                std::fs::create_dir_all(out_path.parent().unwrap())?;
+                let stdout = self.run_cmd(cmd, params, &self.out_dir())?;
+                std::fs::write(&out_path, stdout)?;
+            }
+            DataSource::Url(url_source) => {
+                std::fs::create_dir_all(out_path.parent().unwrap())?;
+                http::fetch(&self.name, url_source, &out_path)?;
+            }
+            DataSource::TextFile { path, pattern } => {
+                report.unmatched_lines = self.refresh_text_file(path, pattern, &out_path)?;
+            }
+            DataSource::TextCmd { cmd, pattern } => {
+                let stdout = self.run_cmd(cmd, params, &self.out_dir())?;
+                let text = String::from_utf8_lossy(&stdout);
+                report.unmatched_lines = self.write_parsed_text(&text, pattern, &out_path)?;
+            }
+            DataSource::MultiJsonCmd(cmd) => {
+                let out_dir = self.out_dir();
+                // Write the tables to an empty directory first, so that tables
+                // which are no longer produced disappear and a failed command
+                // does not clobber the previous ones.
+                let tmp_dir = out_dir.with_file_name(format!("{}.download", self.name));
+                if tmp_dir.exists() {
+                    std::fs::remove_dir_all(&tmp_dir)?;
+                }
+                std::fs::create_dir_all(&tmp_dir)?;
+                let stdout = self.run_cmd(cmd, params, &tmp_dir)?;
+                if !stdout.trim_ascii().is_empty() {
+                    self.write_tables(&stdout, &tmp_dir)?;
+                }
+
+                // A directory cannot be renamed over a non-empty one.
+                let old_dir = out_dir.with_file_name(format!("{}.old", self.name));
+                if old_dir.exists() {
+                    std::fs::remove_dir_all(&old_dir)?;
+                }
+                if out_dir.exists() {
+                    std::fs::rename(&out_dir, &old_dir)?;
+                }
+                std::fs::rename(&tmp_dir, &out_dir)?;
+                if old_dir.exists() {
+                    std::fs::remove_dir_all(&old_dir)?;
+                }
+            }
+        }
 
-            if !output.status.success() {
+        if !self.params.is_empty() {
+            self.evict_cached_params()?;
+        }
+        Ok(report)
+    }
+
+    /// Split a JSON object of named arrays into one file per table.
+    fn write_tables(&self, stdout: &[u8], out_dir: &Path) -> anyhow::Result<()> {
+        let value: serde_json::Value = serde_json::from_slice(stdout).map_err(|e| {
+            anyhow::anyhow!(
+                "Error refreshing source `{}`: expected a JSON object of named arrays: {}",
+                self.name,
+                e
+            )
+        })?;
+        let serde_json::Value::Object(tables) = value else {
+            return Err(anyhow::anyhow!(
+                "Error refreshing source `{}`: expected a JSON object of named arrays",
+                self.name
+            ));
+        };
+        for (table_name, rows) in tables.iter() {
+            if !rows.is_array() {
                 return Err(anyhow::anyhow!(
-                    "Error refreshing source `{}` ({}).\n\n$ {}\n{}",
+                    "Error refreshing source `{}`: `{}` is not an array",
                     self.name,
-                    output.status,
-                    cmd,
-                    String::from_utf8_lossy(&output.stderr)
+                    table_name
                 ));
             }
-            std::fs::write(&out_path, &output.stdout)?;
+            // Table names become file names, so they must not escape `out_dir`.
+            crate::db::validate_table_name(table_name)
+                .map_err(|e| anyhow::anyhow!("Error refreshing source `{}`: {}", self.name, e))?;
+            let path = out_dir.join(format!("{}.json", table_name));
+            std::fs::write(path, serde_json::to_vec(rows)?)?;
         }
         Ok(())
     }
+
+    fn run_cmd(&self, cmd: &str, params: &Params, output_dir: &Path) -> anyhow::Result<Vec<u8>> {
+        let output = std::process::Command::new("bash")
+            .arg("-c")
+            .arg(cmd)
+            .env("FROGTABLE_OUTPUT_DIR", output_dir)
+            .envs(self.param_values(params)?)
+            .output()?;
+
+        output.stderr.lines().for_each(|line| {
+            eprintln!(
+                "[Refreshing {}]: {}",
+                self.name,
+                line.unwrap_or_else(|_| "".to_string())
+            );
+        });
+
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "Error refreshing source `{}` ({}).\n\n$ {}\n{}",
+                self.name,
+                output.status,
+                cmd,
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+
+        Ok(output.stdout)
+    }
+
+    /// Parse a text file, unless neither it nor the pattern changed since it
+    /// was last parsed. Returns the number of lines that did not match.
+    fn refresh_text_file(
+        &self,
+        path: &Path,
+        pattern: &str,
+        out_path: &Path,
+    ) -> anyhow::Result<u64> {
+        let metadata = std::fs::metadata(path)?;
+        let mut parsed_from = ParsedFrom {
+            path: path.to_path_buf(),
+            pattern: pattern.to_string(),
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+            unmatched_lines: 0,
+        };
+        let parsed_from_path = out_path.with_extension("parsed.json");
+        if out_path.exists() {
+            let previous = std::fs::read_to_string(&parsed_from_path)
+                .ok()
+                .and_then(|contents| serde_json::from_str::<ParsedFrom>(&contents).ok());
+            if let Some(previous) = previous.filter(|previous| previous.same_input(&parsed_from)) {
+                return Ok(previous.unmatched_lines);
+            }
+        }
+
+        let text = std::fs::read_to_string(path)?;
+        parsed_from.unmatched_lines = self.write_parsed_text(&text, pattern, out_path)?;
+        std::fs::write(parsed_from_path, serde_json::to_string(&parsed_from)?)?;
+        Ok(parsed_from.unmatched_lines)
+    }
+
+    /// Returns the number of lines that did not match the pattern.
+    fn write_parsed_text(&self, text: &str, pattern: &str, out_path: &Path) -> anyhow::Result<u64> {
+        let regex = text::compile_pattern(pattern)
+            .map_err(|e| anyhow::anyhow!("Invalid pattern for source `{}`: {}", self.name, e))?;
+        let parsed = text::parse_lines(&regex, text)?;
+
+        if parsed.unmatched_count > 0 {
+            eprintln!(
+                "[Refreshing {}]: {} of {} lines did not match the pattern",
+                self.name, parsed.unmatched_count, parsed.line_count
+            );
+        }
+
+        std::fs::create_dir_all(out_path.parent().unwrap())?;
+        std::fs::write(out_path, parsed.ndjson)?;
+        Ok(parsed.unmatched_count as u64)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
 pub struct Query {
     pub name: String,
     pub source: QuerySource,
+    /// Cancel executions that run longer than this.
+    pub timeout_secs: Option<u64>,
+    /// Columns that together identify a row, used to order rows
+    /// unambiguously when paging by cursor.
+    pub key_columns: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ts_rs::TS)]
@@ -127,3 +518,66 @@ impl QuerySource {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn multi_json_cmd(name: &str, cmd: &str) -> Data {
+        Data {
+            name: name.to_string(),
+            source: DataSource::MultiJsonCmd(cmd.to_string()),
+            params: vec![],
+            schema: SourceSchema::default(),
+        }
+    }
+
+    fn table_names(config: &Data) -> Vec<String> {
+        let tables = config.tables(&Params::new()).unwrap();
+        tables.into_iter().map(|table| table.name).collect()
+    }
+
+    #[test]
+    fn failed_multi_json_cmd_keeps_the_previous_tables() {
+        let name = format!("test_multi_{}", std::process::id());
+        let ok = multi_json_cmd(&name, r#"echo '{"a": [{"x": 1}], "b": []}'"#);
+        ok.refresh(&Params::new()).unwrap();
+        assert_eq!(table_names(&ok), ["a", "b"]);
+
+        let failing = multi_json_cmd(
+            &name,
+            r#"echo '[]' > "$FROGTABLE_OUTPUT_DIR/c.json"; exit 1"#,
+        );
+        assert!(failing.refresh(&Params::new()).is_err());
+        assert_eq!(table_names(&failing), ["a", "b"]);
+
+        let fewer = multi_json_cmd(&name, r#"echo '[]' > "$FROGTABLE_OUTPUT_DIR/c.json""#);
+        fewer.refresh(&Params::new()).unwrap();
+        assert_eq!(table_names(&fewer), ["c"]);
+
+        std::fs::remove_dir_all(fewer.out_dir()).unwrap();
+    }
+
+    #[test]
+    fn multi_json_cmd_rejects_unsafe_table_names() {
+        let name = format!("test_multi_unsafe_{}", std::process::id());
+        let ok = multi_json_cmd(&name, r#"echo '{"a": []}'"#);
+        ok.refresh(&Params::new()).unwrap();
+
+        for table_name in ["../escaped", "/tmp/escaped", "a.b"] {
+            let cmd = format!(r#"echo '{{"{}": []}}'"#, table_name);
+            let unsafe_name = multi_json_cmd(&name, &cmd);
+            let error = unsafe_name.refresh(&Params::new()).unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                format!(
+                    "Error refreshing source `{}`: Invalid table name: {}",
+                    name, table_name
+                )
+            );
+            assert_eq!(table_names(&unsafe_name), ["a"]);
+        }
+
+        std::fs::remove_dir_all(ok.out_dir()).unwrap();
+    }
+}
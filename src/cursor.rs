@@ -0,0 +1,292 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::LazyLock;
+
+use duckdb::types::Value;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+/// The key cursors are signed with. It is made up when the server starts,
+/// so cursors do not outlive it.
+static SIGNING_KEY: LazyLock<[u8; 32]> = LazyLock::new(|| {
+    let mut key = [0; 32];
+    getrandom::getrandom(&mut key).expect("Could not generate a key to sign cursors with");
+    key
+});
+
+/// Length in bytes of the signature of a cursor.
+const SIGNATURE_LEN: usize = 32;
+
+fn mac() -> Hmac<Sha256> {
+    Hmac::<Sha256>::new_from_slice(&*SIGNING_KEY).unwrap()
+}
+
+/// An expression the rows of a query are sorted by.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortKey {
+    /// The expression, with its collation if any.
+    pub sql: String,
+    pub descending: bool,
+    pub nulls_first: bool,
+}
+
+impl SortKey {
+    fn reversed(&self) -> Self {
+        Self {
+            sql: self.sql.clone(),
+            descending: !self.descending,
+            nulls_first: !self.nulls_first,
+        }
+    }
+
+    pub fn to_sql(&self) -> String {
+        format!(
+            "{} {} {}",
+            self.sql,
+            if self.descending { "DESC" } else { "ASC" },
+            if self.nulls_first {
+                "NULLS FIRST"
+            } else {
+                "NULLS LAST"
+            }
+        )
+    }
+
+    /// A condition matching the rows sorted after `value`.
+    fn after_sql(&self, value: &Option<String>, cast: &str) -> String {
+        let operator = if self.descending { "<" } else { ">" };
+        match (value, self.nulls_first) {
+            (None, true) => format!("({}) IS NOT NULL", self.sql),
+            (None, false) => "FALSE".to_string(),
+            (Some(_), true) => format!("({}) {} {}", self.sql, operator, cast),
+            (Some(_), false) => format!(
+                "(({}) {} {} OR ({}) IS NULL)",
+                self.sql, operator, cast, self.sql
+            ),
+        }
+    }
+}
+
+/// `ORDER BY` items for `keys`, or for the opposite order.
+pub fn order_by_sql(keys: &[SortKey], reverse: bool) -> String {
+    keys.iter()
+        .map(|key| match reverse {
+            true => key.reversed().to_sql(),
+            false => key.to_sql(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn hash_keys(keys: &[SortKey]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for key in keys {
+        key.sql.hash(&mut hasher);
+        key.descending.hash(&mut hasher);
+        key.nulls_first.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// The position of a row among the sorted rows of a query, from which the
+/// next or previous page is fetched. Clients get it as an opaque string,
+/// signed so that they cannot forge one.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Cursor {
+    /// Identifies the sort keys, so that a cursor cannot be used with
+    /// another ordering.
+    keys: u64,
+    /// Whether the page is made of the rows before the position rather than
+    /// after it.
+    pub backward: bool,
+    /// The value of each sort key at the position, as text.
+    values: Vec<Option<String>>,
+}
+
+impl Cursor {
+    pub fn new(keys: &[SortKey], backward: bool, values: Vec<Option<String>>) -> Self {
+        Self {
+            keys: hash_keys(keys),
+            backward,
+            values,
+        }
+    }
+
+    /// The cursor, followed by its signature, in hexadecimal.
+    pub fn encode(&self) -> String {
+        let mut bytes = serde_json::to_vec(self).unwrap();
+        let mut mac = mac();
+        mac.update(&bytes);
+        bytes.extend(mac.finalize().into_bytes());
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    /// Decode a cursor from `encode`, checking that it was made by this
+    /// server for `keys`.
+    pub fn decode(cursor: &str, keys: &[SortKey]) -> anyhow::Result<Self> {
+        let invalid = || anyhow::anyhow!("Invalid cursor");
+        if !cursor.len().is_multiple_of(2) || !cursor.is_ascii() {
+            return Err(invalid());
+        }
+        let bytes = (0..cursor.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&cursor[i..i + 2], 16))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid())?;
+        let Some(payload_len) = bytes.len().checked_sub(SIGNATURE_LEN) else {
+            return Err(invalid());
+        };
+        let (payload, signature) = bytes.split_at(payload_len);
+        let mut mac = mac();
+        mac.update(payload);
+        mac.verify_slice(signature).map_err(|_| invalid())?;
+        let cursor: Self = serde_json::from_slice(payload).map_err(|_| invalid())?;
+        if cursor.keys != hash_keys(keys) || cursor.values.len() != keys.len() {
+            return Err(anyhow::anyhow!(
+                "The cursor was made for another ordering of the query"
+            ));
+        }
+        Ok(cursor)
+    }
+
+    /// A condition matching the rows past the cursor, in the direction it
+    /// points to. Its values are appended to `params`, each cast to the
+    /// DuckDB type of its key in `types`.
+    pub fn to_sql(&self, keys: &[SortKey], types: &[String], params: &mut Vec<Value>) -> String {
+        let keys = keys
+            .iter()
+            .map(|key| match self.backward {
+                true => key.reversed(),
+                false => key.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        let casts = types
+            .iter()
+            .map(|data_type| format!("CAST(? AS {})", data_type))
+            .collect::<Vec<_>>();
+        let mut push_param = |value: &Option<String>| {
+            if let Some(value) = value {
+                params.push(Value::Text(value.clone()));
+            }
+        };
+
+        // Past the cursor means equal on the first keys, then after it on
+        // the next one.
+        let mut alternatives = vec![];
+        for i in 0..keys.len() {
+            let mut conditions = vec![];
+            for j in 0..i {
+                let value = &self.values[j];
+                push_param(value);
+                conditions.push(match value {
+                    Some(_) => format!("({}) = {}", keys[j].sql, casts[j]),
+                    None => format!("({}) IS NULL", keys[j].sql),
+                });
+            }
+            let value = &self.values[i];
+            push_param(value);
+            conditions.push(keys[i].after_sql(value, &casts[i]));
+            alternatives.push(format!("({})", conditions.join(" AND ")));
+        }
+        if alternatives.is_empty() {
+            return "FALSE".to_string();
+        }
+        format!("({})", alternatives.join(" OR "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys() -> Vec<SortKey> {
+        vec![
+            SortKey {
+                sql: "\"age\"".to_string(),
+                descending: true,
+                nulls_first: false,
+            },
+            SortKey {
+                sql: "\"id\"".to_string(),
+                descending: false,
+                nulls_first: false,
+            },
+        ]
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    fn unhex(text: &str) -> Vec<u8> {
+        (0..text.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn decodes_its_own_cursors() {
+        let values = vec![Some("42".to_string()), None];
+        let encoded = Cursor::new(&keys(), true, values.clone()).encode();
+
+        let cursor = Cursor::decode(&encoded, &keys()).unwrap();
+
+        assert!(cursor.backward);
+        assert_eq!(cursor.values, values);
+    }
+
+    #[test]
+    fn rejects_tampered_cursors() {
+        let encoded = Cursor::new(&keys(), false, vec![Some("42".to_string()), None]).encode();
+        let bytes = unhex(&encoded);
+        let (payload, signature) = bytes.split_at(bytes.len() - SIGNATURE_LEN);
+        let tampered = String::from_utf8(payload.to_vec())
+            .unwrap()
+            .replace("\"42\"", "\"0) OR (1=1\"");
+
+        let result = Cursor::decode(&hex(&[tampered.as_bytes(), signature].concat()), &keys());
+
+        assert_eq!(result.unwrap_err().to_string(), "Invalid cursor");
+    }
+
+    #[test]
+    fn rejects_unsigned_cursors() {
+        let payload = serde_json::to_vec(&Cursor::new(&keys(), false, vec![None, None])).unwrap();
+
+        assert!(Cursor::decode(&hex(&payload), &keys()).is_err());
+        assert!(Cursor::decode("", &keys()).is_err());
+        assert!(Cursor::decode("zz", &keys()).is_err());
+    }
+
+    #[test]
+    fn rejects_cursors_for_another_ordering() {
+        let encoded = Cursor::new(&keys(), false, vec![None, None]).encode();
+
+        let result = Cursor::decode(&encoded, &keys()[1..]);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn casts_values_to_the_given_types() {
+        let cursor = Cursor::new(&keys(), false, vec![Some("42".to_string()), None]);
+        let mut params = vec![];
+
+        let sql = cursor.to_sql(
+            &keys(),
+            &["INTEGER".to_string(), "BIGINT".to_string()],
+            &mut params,
+        );
+
+        assert_eq!(
+            sql,
+            "((((\"age\") < CAST(? AS INTEGER) OR (\"age\") IS NULL)) OR ((\"age\") = CAST(? AS INTEGER) AND FALSE))"
+        );
+        assert_eq!(
+            params,
+            [Value::Text("42".to_string()), Value::Text("42".to_string())]
+        );
+    }
+}
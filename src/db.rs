@@ -3,28 +3,74 @@ use duckdb::{params, Config as DuckConfig, Connection};
 use fallible_iterator::FallibleIterator;
 use notify::{event::ModifyKind, Event, EventKind, RecursiveMode, Result, Watcher};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ffi::OsString;
+use std::hash::{Hash, Hasher};
 use std::sync::mpsc;
 use tokio::sync::broadcast;
 use ts_rs::TS;
 
+use crate::aggregate::{self, Aggregation};
+use crate::cache::{CacheStats, ResultCache};
 use crate::cli_config::CliConfig;
 use crate::config;
+use crate::cursor::{self, Cursor, SortKey};
+use crate::deps;
+use crate::filter::{self, Filter};
+use crate::graph;
 use crate::json;
+use crate::leases::{Lease, Leases};
+use crate::lineage;
+use crate::pool::Pool;
+use crate::rejects;
+use crate::schema;
+use crate::summary;
+
+/// Column names and DuckDB types, in order.
+pub type Columns = Vec<(String, String)>;
+
+/// Fewest connections in the pool, even on a single core, so that a slow
+/// query does not hold up everything else.
+const MIN_POOL_SIZE: usize = 4;
 
 #[derive(Clone)]
 pub struct DB {
-    pub conn: std::sync::Arc<std::sync::Mutex<Connection>>,
+    pub pool: std::sync::Arc<Pool>,
+    /// Held while a source refreshes, so that concurrent requests do not
+    /// refresh the same source at once.
+    refresh_locks:
+        std::sync::Arc<std::sync::Mutex<HashMap<String, std::sync::Arc<std::sync::Mutex<()>>>>>,
     pub tx: broadcast::Sender<DbBroadcastEvent>,
     pub config: std::sync::Arc<config::RootConfig>,
+    pub statuses: std::sync::Arc<std::sync::Mutex<HashMap<String, SourceStatus>>>,
+    /// Status of the last run of each initializer and query, by graph node
+    /// id.
+    pub run_statuses: std::sync::Arc<std::sync::Mutex<HashMap<String, RunStatus>>>,
+    views: std::sync::Arc<std::sync::Mutex<HashMap<String, ViewState>>>,
+    /// Deferred counts by token, `None` while running.
+    counts: std::sync::Arc<std::sync::Mutex<HashMap<String, Option<u32>>>>,
+    /// Hash of the data of each source as of its last refresh, by source name
+    /// and parameter values, so that cached results are only reused while
+    /// the data they came from is unchanged.
+    data_versions: std::sync::Arc<std::sync::Mutex<HashMap<(String, String), DataVersion>>>,
+    /// Keep the views of parameterized sources on the parameter values of
+    /// the requests reading them.
+    leases: std::sync::Arc<Leases>,
+    /// The parameter values the views of each parameterized source were last
+    /// created for.
+    view_params: std::sync::Arc<std::sync::Mutex<HashMap<String, String>>>,
+    results: std::sync::Arc<std::sync::Mutex<ResultCache>>,
+    /// The schema DuckDB inferred for each source table on its last refresh.
+    pub source_schemas: std::sync::Arc<std::sync::Mutex<HashMap<String, Columns>>>,
+    // Only held so that the watcher is not dropped.
+    #[allow(dead_code)]
     pub watcher: std::sync::Arc<std::sync::Mutex<notify::RecommendedWatcher>>,
 }
 
 //
 
 impl DB {
-    pub async fn new_from_cli_args() -> anyhow::Result<Self> {
-        let args: Vec<OsString> = std::env::args_os().collect();
+    pub async fn new_from_cli_args(args: Vec<OsString>) -> anyhow::Result<Self> {
         let prog_name = args[0].clone();
         let args_without_prog_name = &args[1..];
 
@@ -48,10 +94,16 @@ impl DB {
             DuckConfig::default().allow_unsigned_extensions()?,
         )?;
 
+        let pool_size = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+            .max(MIN_POOL_SIZE);
+
         let (tx, _) = broadcast::channel::<DbBroadcastEvent>(16);
 
         let (file_watch_tx, file_watch_rx) = mpsc::channel::<Result<Event>>();
 
+        let results = ResultCache::new(root_config.cache_size_mb * 1024 * 1024);
         let root_config_arc = std::sync::Arc::new(root_config);
 
         let mut watcher = notify::recommended_watcher(file_watch_tx)?;
@@ -62,284 +114,2778 @@ impl DB {
             }
         }
 
-        spawn_file_watcher(file_watch_rx, tx.clone(), root_config_arc.clone());
         spawn_keepalives(tx.clone());
 
         let db = DB {
-            conn: std::sync::Arc::new(conn.into()),
+            pool: std::sync::Arc::new(Pool::new(conn, pool_size)?),
+            refresh_locks: Default::default(),
+            views: Default::default(),
+            counts: Default::default(),
+            data_versions: Default::default(),
+            leases: Default::default(),
+            view_params: Default::default(),
+            results: std::sync::Arc::new(std::sync::Mutex::new(results)),
             config: root_config_arc,
-            tx: tx,
+            statuses: Default::default(),
+            source_schemas: Default::default(),
+            run_statuses: Default::default(),
+            tx,
             watcher: std::sync::Arc::new(std::sync::Mutex::new(watcher)),
         };
 
         db.init()?;
 
+        spawn_file_watcher(file_watch_rx, db.clone());
+
         Ok(db)
     }
 
     pub fn init(&self) -> anyhow::Result<()> {
-        for config in self.config.initializers.iter() {
-            self.conn
-                .lock()
-                .unwrap()
-                .execute_batch(&config.source.sql()?)?;
+        for (idx, config) in self.config.initializers.iter().enumerate() {
+            let started_at = std::time::Instant::now();
+            let result = config
+                .source
+                .sql()
+                .map_err(anyhow::Error::from)
+                .and_then(|sql| {
+                    let conn = self.pool.write(&CancelToken::default())?;
+                    Ok(conn.execute_batch(&sql)?)
+                });
+            self.record_run(
+                &graph::initializer_id(idx),
+                started_at,
+                result.as_ref().err(),
+            );
+            result?;
         }
-        self.refresh_sources("all")?;
-        for config in self.config.sources.iter() {
-            validate_table_name(&config.name)?;
-            let escaped_table_name = escape_table_name(&config.name);
-
-            self.conn.lock().unwrap().execute(
-                    &format!(
-                        "CREATE OR REPLACE VIEW {} AS SELECT * FROM read_json('{}', ignore_errors = true, format = 'unstructured');",
-                        escaped_table_name,
-                        config.path().display(),
-                    ),
-                    params![],
-                )?;
+        self.pool.write(&CancelToken::default())?.execute_batch(
+            "CREATE TABLE IF NOT EXISTS frogtable_rejects (source_table VARCHAR, line BIGINT, error VARCHAR, raw VARCHAR);",
+        )?;
+        self.refresh_sources("all", &config::Params::new(), &CancelToken::default())?;
+
+        // A broken query should not prevent the others from being served.
+        if let Err(e) = self.create_query_views() {
+            eprintln!("{}", e);
         }
 
         Ok(())
     }
 
-    pub fn refresh_sources(&self, query_name: &str) -> anyhow::Result<()> {
-        let sources = if query_name == "all" {
-            &self.config.sources
-        } else {
-            &self.find_dependent_sources(query_name)?
-        };
-        for config in sources.iter() {
-            config.refresh()?;
+    /// The queries each query references directly, by query name.
+    pub fn query_graph(&self) -> anyhow::Result<BTreeMap<String, Vec<String>>> {
+        let mut graph = BTreeMap::new();
+        for query in self.config.queries.iter() {
+            let referenced_tables = deps::referenced_tables(&query.source.sql()?);
+            let dependencies = self
+                .config
+                .queries
+                .iter()
+                .filter(|other| other.name != query.name)
+                .filter(|other| referenced_tables.contains(&deps::normalize_name(&other.name)))
+                .map(|other| other.name.clone())
+                .collect();
+            graph.insert(query.name.clone(), dependencies);
         }
+        Ok(graph)
+    }
 
-        Ok(())
+    /// The queries that read from `query_name`, directly or transitively.
+    pub fn downstream_queries(&self, query_name: &str) -> anyhow::Result<Vec<String>> {
+        let graph = self.query_graph()?;
+        let mut downstream: Vec<String> = vec![];
+        let mut pending = vec![query_name.to_string()];
+        while let Some(name) = pending.pop() {
+            for (other, dependencies) in graph.iter() {
+                if dependencies.contains(&name)
+                    && other != query_name
+                    && !downstream.contains(other)
+                {
+                    downstream.push(other.clone());
+                    pending.push(other.clone());
+                }
+            }
+        }
+        Ok(downstream)
     }
 
-    pub fn exec_query(
-        &self,
-        name: &str,
-        page: u32,
-        per_page: u32,
-        order_by: &[Ordering],
-        filters: &str,
-    ) -> anyhow::Result<ExecQueryResult> {
-        let query = self
-            .config
-            .queries
-            .iter()
-            .find(|config| config.name == name)
-            .ok_or(anyhow::anyhow!("Query not found"))?;
+    /// The full graph of sources, initializers and queries, with the status of
+    /// their last refresh or run.
+    pub fn dependency_graph(&self) -> anyhow::Result<graph::Graph> {
+        let mut graph = graph::Graph::default();
+        let run_statuses = self.run_statuses.lock().unwrap().clone();
+        let node = |id: String, kind: graph::NodeKind, name: String| {
+            let status = run_statuses.get(&id).cloned().unwrap_or_default();
+            graph::GraphNode {
+                id,
+                kind,
+                name,
+                last_run_at: status.last_run_at,
+                last_run_ms: status.last_run_ms,
+                error: status.error,
+            }
+        };
 
-        validate_table_name(&query.name)?;
-        let escaped_table_name = escape_table_name(&query.name);
+        // Which node provides each table that queries can read from.
+        let mut providers: Vec<(String, String)> = vec![];
 
-        let sql = query.source.sql()?;
+        let statuses = self.statuses.lock().unwrap().clone();
+        for config in self.config.sources.iter() {
+            let id = graph::source_id(&config.name);
+            let status = statuses.get(&config.name);
+            graph.nodes.push(graph::GraphNode {
+                id: id.clone(),
+                kind: graph::NodeKind::Source,
+                name: config.name.clone(),
+                last_run_at: status.and_then(|s| s.last_refreshed_at.clone()),
+                last_run_ms: status.and_then(|s| s.last_refresh_ms),
+                error: status.and_then(|s| s.error.clone()),
+            });
+            for table in config.table_names()? {
+                providers.push((deps::normalize_name(&table), id.clone()));
+            }
+        }
 
-        self.conn.lock().unwrap().execute(
-            &format!("CREATE OR REPLACE VIEW {} AS {}", escaped_table_name, sql,),
-            params![],
-        )?;
+        for (idx, config) in self.config.initializers.iter().enumerate() {
+            let id = graph::initializer_id(idx);
+            let sql = config.source.sql()?;
+            let name = match config.source.path() {
+                Some(path) => path.display().to_string(),
+                None => sql.lines().next().unwrap_or_default().trim().to_string(),
+            };
+            graph
+                .nodes
+                .push(node(id.clone(), graph::NodeKind::Initializer, name));
+            for table in deps::created_tables(&sql) {
+                providers.push((table, id.clone()));
+            }
+        }
 
-        let order_clause = if order_by.len() > 0 {
-            let order_by_str = order_by
-                .iter()
-                .map(|ordering| ordering.to_sql())
-                .collect::<Vec<_>>()
-                .join(", ");
-            format!("ORDER BY {}", order_by_str)
-        } else {
-            "".to_string()
-        };
+        for query in self.config.queries.iter() {
+            let id = graph::query_id(&query.name);
+            graph
+                .nodes
+                .push(node(id.clone(), graph::NodeKind::Query, query.name.clone()));
+            providers.push((deps::normalize_name(&query.name), id));
+        }
 
-        let where_clause = if filters.is_empty() {
-            "".to_string()
-        } else {
-            format!("WHERE {}", filters)
-        };
+        for query in self.config.queries.iter() {
+            let id = graph::query_id(&query.name);
+            let referenced_tables = deps::referenced_tables(&query.source.sql()?);
+            for (table, provider) in providers.iter() {
+                if referenced_tables.contains(table) && *provider != id {
+                    graph.add_edge(provider.clone(), id.clone());
+                }
+            }
+        }
 
-        let wrapped_sql = format!(
-            "SELECT * FROM {} {} {} LIMIT {} OFFSET {};",
-            escaped_table_name,
-            where_clause,
-            order_clause,
-            per_page,
-            (page - 1) * per_page
-        );
+        Ok(graph)
+    }
 
-        let conn = self.conn.lock().unwrap();
+    /// The columns of a query, without running it.
+    pub fn describe_query(&self, name: &str) -> anyhow::Result<Vec<schema::ColumnSchema>> {
+        let query = self.find_query(name)?;
+        self.query_view(query, &CancelToken::default())?;
 
-        let mut count_stmt = conn.prepare(&format!(
-            "SELECT COUNT(*) FROM {} {};",
-            escaped_table_name, where_clause
-        ))?;
+        let conn = self.pool.read();
+        let comments = conn
+            .prepare(
+                "SELECT column_name, comment FROM duckdb_columns() WHERE schema_name = 'main' AND table_name = ? AND comment IS NOT NULL;",
+            )?
+            .query(params![query.name])?
+            .map(|row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+            .collect::<HashMap<_, _>>()?;
+        let columns = conn
+            .prepare(&format!("DESCRIBE {};", escape_table_name(&query.name)))?
+            .query([])?
+            .map(|row| {
+                let name: String = row.get(0)?;
+                let data_type: String = row.get(1)?;
+                let null: String = row.get(2)?;
+                Ok(schema::ColumnSchema {
+                    comment: comments.get(&name).cloned(),
+                    column_type: schema::ColumnType::parse(&data_type),
+                    nullable: null != "NO",
+                    name,
+                    data_type,
+                })
+            })
+            .collect::<Vec<_>>()?;
+        Ok(columns)
+    }
 
-        let total_count: u32 = count_stmt.query([])?.next()?.unwrap().get(0)?;
+    /// The upstream source columns of each column of a query's result.
+    pub fn query_lineage(&self, name: &str) -> anyhow::Result<Vec<lineage::ColumnLineage>> {
+        let query = self.find_query(name)?;
 
-        drop(count_stmt);
+        let mut catalog = lineage::Catalog::default();
+        let mut tables = vec![];
+        for config in self.config.sources.iter() {
+            tables.extend(config.table_names()?);
+        }
+        for config in self.config.initializers.iter() {
+            tables.extend(deps::created_tables(&config.source.sql()?));
+        }
+        for table in tables {
+            // Tables that do not exist yet, such as those of parameterized
+            // sources that have not been queried, are left out.
+            if let Ok(columns) = self.describe(&escape_table_name(&table)) {
+                let columns = columns.into_iter().map(|(name, _)| name).collect();
+                catalog.tables.insert(deps::normalize_name(&table), columns);
+            }
+        }
+        for query in self.config.queries.iter() {
+            catalog
+                .queries
+                .insert(deps::normalize_name(&query.name), query.source.sql()?);
+        }
 
-        // This is kind of a hack, but it is only possible to get a schema after
-        // executing a query.
-        let mut stmt: duckdb::Statement<'_> = conn.prepare(&wrapped_sql)?;
-        let rows = stmt.query([])?;
-        let results = rows
-            .map(|r| json::duckdb_row_to_json(r))
-            .collect::<Vec<_>>()?;
+        let mut lineage = lineage::analyze(&query.source.sql()?, &catalog)?;
 
-        let schema: std::sync::Arc<duckdb::arrow::datatypes::Schema> = stmt.schema();
+        // Report the columns under the names DuckDB actually gives them.
+        let columns = self.query_view(query, &CancelToken::default())?.columns;
+        if columns.len() == lineage.len() {
+            for (column, (name, _)) in lineage.iter_mut().zip(columns) {
+                column.column = name;
+            }
+            return Ok(lineage);
+        }
+        Ok(columns
+            .into_iter()
+            .map(|(name, _)| {
+                let upstream = lineage
+                    .iter()
+                    .find(|column| {
+                        deps::normalize_name(&column.column) == deps::normalize_name(&name)
+                    })
+                    .map(|column| column.upstream.clone())
+                    .unwrap_or_default();
+                lineage::ColumnLineage {
+                    column: name,
+                    upstream,
+                }
+            })
+            .collect())
+    }
+
+    /// (Re)create the view of every query, upstream queries first, so that
+    /// queries can select from each other. Queries in (or downstream of) a
+    /// dependency cycle are reported without holding up the others.
+    pub fn create_query_views(&self) -> anyhow::Result<()> {
+        let mut errors = vec![];
+        for query in self.config.queries.iter() {
+            if let Err(e) = self.query_view(query, &CancelToken::default()) {
+                errors.push(format!("`{}`: {}", query.name, e));
+            }
+        }
 
-        let result = ExecQueryResult {
-            total_count: total_count,
-            data: results,
-            schema: schema.as_ref().clone(),
+        if !errors.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Error creating query views.\n\n{}",
+                errors.join("\n")
+            ));
+        }
+        Ok(())
+    }
+
+    /// The view of `query`, (re)created if its SQL or any query view it
+    /// selects from changed since it was last created.
+    fn query_view(&self, query: &config::Query, cancel: &CancelToken) -> anyhow::Result<ViewState> {
+        // Read the modification time before the file, so that a later edit
+        // is not missed.
+        let modified = query
+            .source
+            .path()
+            .and_then(|path| std::fs::metadata(path).ok()?.modified().ok());
+        let previous = self.views.lock().unwrap().get(&query.name).cloned();
+
+        // Only read the SQL again when the file changed.
+        let unchanged = previous.as_ref().filter(|state| {
+            query.source.path().is_none() || (modified.is_some() && state.modified == modified)
+        });
+        let (sql, sql_hash, upstream_names) = match unchanged {
+            Some(state) => (
+                None,
+                state.sql_hash,
+                state
+                    .upstream
+                    .iter()
+                    .map(|(name, _)| name.clone())
+                    .collect(),
+            ),
+            None => {
+                let sql = query.source.sql()?;
+                let sql_hash = hash_sql(&sql);
+                // Report cycles clearly rather than as a missing table.
+                let graph = self.query_graph()?;
+                deps::dependency_order(&graph, [query.name.as_str()])?;
+                let upstream_names = graph.get(&query.name).cloned().unwrap_or_default();
+                (Some(sql), sql_hash, upstream_names)
+            }
         };
 
-        Ok(result)
+        let mut upstream = vec![];
+        for name in upstream_names {
+            let state = self.query_view(self.find_query(&name)?, cancel)?;
+            upstream.push((name, state.version));
+        }
+
+        if let Some(state) = previous {
+            if state.sql_hash == sql_hash && state.upstream == upstream {
+                if state.modified != modified {
+                    if let Some(state) = self.views.lock().unwrap().get_mut(&query.name) {
+                        state.modified = modified;
+                    }
+                }
+                return Ok(state);
+            }
+        }
+
+        let sql = match sql {
+            Some(sql) => sql,
+            None => query.source.sql()?,
+        };
+        let conn = self.pool.write(cancel)?;
+        create_query_view(&conn, &query.name, &sql)?;
+        drop(conn);
+        let state = ViewState {
+            sql_hash,
+            modified,
+            version: VIEW_VERSION.fetch_add(1, std::sync::atomic::Ordering::SeqCst),
+            upstream,
+            columns: self.describe(&escape_table_name(&query.name))?,
+        };
+        self.views
+            .lock()
+            .unwrap()
+            .insert(query.name.clone(), state.clone());
+        Ok(state)
     }
 
-    pub fn find_dependent_sources(&self, query_name: &str) -> anyhow::Result<Vec<config::Data>> {
-        let query = self
-            .config
+    fn find_query(&self, name: &str) -> anyhow::Result<&config::Query> {
+        self.config
             .queries
             .iter()
-            .find(|config| config.name == query_name)
-            .ok_or(anyhow::anyhow!("Query not found"))?;
+            .find(|config| config.name == name)
+            .ok_or(anyhow::anyhow!("Query not found"))
+    }
+
+    /// (Re)create the views for every table of a source. This runs after each
+    /// refresh, since sources with several tables may gain new ones.
+    fn create_source_views(
+        conn: &Connection,
+        config: &config::Data,
+        params: &config::Params,
+        inferred_schemas: &HashMap<String, Columns>,
+    ) -> anyhow::Result<()> {
+        for table in config.tables(params)? {
+            validate_table_name(&table.name)?;
+            let escaped_table_name = escape_table_name(&table.name);
+            let inferred_columns = inferred_schemas
+                .get(&table.name)
+                .map(|columns| columns.as_slice())
+                .unwrap_or_default();
+
+            conn.execute(
+                &format!(
+                    "CREATE OR REPLACE VIEW {} AS {};",
+                    escaped_table_name,
+                    source_view_sql(config, &table, inferred_columns)?,
+                ),
+                params![],
+            )?;
+        }
+
+        Ok(())
+    }
 
-        let mut dependent_sources = vec![];
+    /// Infer the schema of each table of a source and compare it with the
+    /// previous refresh. Changes are broadcast as `SchemaChanged`, or fail the
+    /// refresh if the source has a strict schema.
+    fn check_source_schema(
+        &self,
+        config: &config::Data,
+        params: &config::Params,
+    ) -> anyhow::Result<HashMap<String, Columns>> {
+        let mut inferred_schemas = HashMap::new();
+        for table in config.tables(params)? {
+            let columns = self.describe(&format!("SELECT * FROM {}", read_source_sql(&table)))?;
+            inferred_schemas.insert(table.name.clone(), columns);
+        }
 
-        let sql_source = query.source.sql()?;
+        if config.schema.strict {
+            for column in config.schema.columns.iter() {
+                let found = inferred_schemas
+                    .values()
+                    .any(|columns| columns.iter().any(|(name, _)| name == &column.name));
+                if !found {
+                    return Err(anyhow::anyhow!(
+                        "Schema of source `{}` is missing the declared column `{}`",
+                        config.name,
+                        column.name
+                    ));
+                }
+            }
+        }
 
-        for data_source in self.config.sources.iter() {
-            if sql_source.contains(&data_source.name) {
-                dependent_sources.push(data_source.clone());
+        let mut source_schemas = self.source_schemas.lock().unwrap();
+        let mut events = vec![];
+        for (table_name, columns) in inferred_schemas.iter() {
+            let Some(previous) = source_schemas.get(table_name) else {
+                continue;
+            };
+            let changes = diff_columns(previous, columns);
+            if changes.is_empty() {
+                continue;
+            }
+            if config.schema.strict {
+                return Err(anyhow::anyhow!(
+                    "Schema of source `{}` changed: {}",
+                    table_name,
+                    changes
+                        .iter()
+                        .map(|change| change.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
             }
+            events.push(DbBroadcastEvent::SchemaChanged {
+                name: table_name.clone(),
+                columns: changes,
+            });
+        }
+        for (table_name, columns) in inferred_schemas.iter() {
+            source_schemas.insert(table_name.clone(), columns.clone());
         }
+        drop(source_schemas);
 
-        Ok(dependent_sources)
-    }
+        // Views bind the columns they select, so query views have to be
+        // created again against the new schema.
+        if !events.is_empty() {
+            self.views.lock().unwrap().clear();
+        }
 
-    pub fn list_queries(&self) -> anyhow::Result<Vec<config::Query>> {
-        Ok(self.config.queries.clone())
+        for event in events {
+            let _ = self.tx.send(event);
+        }
+
+        Ok(inferred_schemas)
     }
-}
 
-pub struct ExecQueryResult {
-    pub total_count: u32,
-    pub data: Vec<Vec<serde_json::Value>>,
-    pub schema: duckdb::arrow::datatypes::Schema,
-}
+    /// Column names and DuckDB types of the result of `sql`.
+    fn describe(&self, sql: &str) -> anyhow::Result<Columns> {
+        let conn = self.pool.read();
+        let mut stmt = conn.prepare(&format!("DESCRIBE {}", sql))?;
+        let columns = stmt
+            .query([])?
+            .map(|row| Ok((row.get(0)?, row.get(1)?)))
+            .collect::<Vec<_>>()?;
+        Ok(columns)
+    }
 
-#[derive(TS, Serialize, Deserialize, Debug, Clone)]
-#[serde(tag = "eventType")]
-#[ts(export)]
-pub enum DbBroadcastEvent {
-    Ping { data: String },
-    QueryUpdated { name: String },
-}
+    /// Refresh the sources a query depends on, or every source for "all".
+    /// Parameterized sources are skipped for "all", since they can only be
+    /// refreshed once a query supplies their parameters.
+    ///
+    /// The returned leases keep the views of parameterized sources on
+    /// `params`, and must be held until the query has run.
+    pub fn refresh_sources(
+        &self,
+        query_name: &str,
+        params: &config::Params,
+        cancel: &CancelToken,
+    ) -> anyhow::Result<Vec<Lease>> {
+        if query_name == "all" {
+            let sources = self
+                .config
+                .sources
+                .iter()
+                .filter(|config| config.params.is_empty())
+                .cloned()
+                .collect::<Vec<_>>();
+            self.refresh_each(&sources, params, cancel)?;
+            return Ok(vec![]);
+        }
+        let sources = self.find_dependent_sources(query_name)?;
+        let leases = self.lease_sources(&sources, params, cancel)?;
+        self.refresh_each(&sources, params, cancel)?;
+        Ok(leases)
+    }
 
-// I tried format_sql_query crate but it does not add quotes if hyphens are
-// present.
-fn escape_table_name(name: &str) -> String {
-    return format!("\"{}\"", name);
-}
+    /// Lease the parameterized sources a query depends on for `params`,
+    /// refreshing those whose views were last created for other values.
+    fn lease_query_sources(
+        &self,
+        query_name: &str,
+        params: &config::Params,
+        cancel: &CancelToken,
+    ) -> anyhow::Result<Vec<Lease>> {
+        let sources = self.find_dependent_sources(query_name)?;
+        let leases = self.lease_sources(&sources, params, cancel)?;
+        let view_params = self.view_params.lock().unwrap().clone();
+        let mut stale = vec![];
+        for config in sources
+            .into_iter()
+            .filter(|config| !config.params.is_empty())
+        {
+            if view_params.get(&config.name) != Some(&config.params_key(params)?) {
+                stale.push(config);
+            }
+        }
+        self.refresh_each(&stale, params, cancel)?;
+        Ok(leases)
+    }
 
-fn validate_table_name(name: &str) -> anyhow::Result<()> {
-    // name should only contain a-zA-Z0-9-_
-    if !name
-        .chars()
-        .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
-    {
-        return Err(anyhow::anyhow!("Invalid table name: {}", name));
+    fn lease_sources(
+        &self,
+        sources: &[config::Data],
+        params: &config::Params,
+        cancel: &CancelToken,
+    ) -> anyhow::Result<Vec<Lease>> {
+        // Always in the same order, so that two requests never wait for each
+        // other's leases.
+        let mut sources = sources
+            .iter()
+            .filter(|config| !config.params.is_empty())
+            .collect::<Vec<_>>();
+        sources.sort_by(|a, b| a.name.cmp(&b.name));
+        sources
+            .into_iter()
+            .map(|config| {
+                self.leases
+                    .acquire(&config.name, &config.params_key(params)?, cancel)
+            })
+            .collect()
     }
-    Ok(())
-}
 
-#[derive(TS, Serialize, Deserialize, Debug)]
-pub struct Ordering {
-    column: String,
-    direction: Direction,
-}
+    fn refresh_each(
+        &self,
+        sources: &[config::Data],
+        params: &config::Params,
+        cancel: &CancelToken,
+    ) -> anyhow::Result<()> {
+        for config in sources.iter() {
+            let started_at = std::time::Instant::now();
+            let result = self.refresh_source(config, params, cancel);
 
-impl Ordering {
-    pub fn to_sql(&self) -> String {
-        format!(
-            "\"{}\" {}",
-            self.column,
-            match self.direction {
-                Direction::Asc => "ASC",
-                Direction::Desc => "DESC",
+            let mut statuses = self.statuses.lock().unwrap();
+            let status = statuses
+                .entry(config.name.clone())
+                .or_insert_with(|| SourceStatus::new(&config.name));
+            status.last_refreshed_at = Some(chrono::Utc::now().to_rfc3339());
+            status.last_refresh_ms = Some(started_at.elapsed().as_millis() as u64);
+            match &result {
+                Ok(Some(Refreshed { rejects, report })) => {
+                    status.error = None;
+                    status.rejects_count = rejects.iter().map(|(_, count)| count).sum();
+                    status.rejects_views = rejects.iter().map(|(view, _)| view.clone()).collect();
+                    status.unmatched_lines = report.unmatched_lines;
+                }
+                Ok(None) => status.error = None,
+                Err(e) => status.error = Some(e.to_string()),
             }
-        )
+            drop(statuses);
+
+            result?;
+        }
+
+        Ok(())
     }
-}
 
-#[derive(TS, Serialize, Deserialize, Debug)]
-pub enum Direction {
-    Asc,
-    Desc,
-}
+    /// Refresh a single source. Its views and rejects are only replaced if
+    /// its data changed or they were created for other parameter values;
+    /// otherwise this returns `None`.
+    fn refresh_source(
+        &self,
+        config: &config::Data,
+        params: &config::Params,
+        cancel: &CancelToken,
+    ) -> anyhow::Result<Option<Refreshed>> {
+        let lock = self
+            .refresh_locks
+            .lock()
+            .unwrap()
+            .entry(config.name.clone())
+            .or_default()
+            .clone();
+        let _refreshing = lock.lock().unwrap();
 
-fn spawn_keepalives(tx: broadcast::Sender<DbBroadcastEvent>) {
-    tokio::spawn(async move {
-        loop {
-            let _ = tx.send(DbBroadcastEvent::Ping {
-                data: "keepalive".to_string(),
-            });
-            tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+        let report = config.refresh(params)?;
+        let params_key = config.params_key(params)?;
+        let tables = config.tables(params)?;
+        let version_key = (config.name.clone(), params_key.clone());
+        let views_current = config.params.is_empty()
+            || self.view_params.lock().unwrap().get(&config.name) == Some(&params_key);
+        let previous = self
+            .data_versions
+            .lock()
+            .unwrap()
+            .get(&version_key)
+            .copied();
+
+        // Files that look untouched, e.g. after `304 Not Modified`, are not
+        // read again.
+        let stat = stat_tables(&tables)?;
+        if views_current && previous.is_some_and(|previous| previous.stat == stat) {
+            return Ok(None);
+        }
+        let data_version = DataVersion {
+            stat,
+            hash: hash_tables(&tables)?,
+        };
+        if views_current && previous.is_some_and(|previous| previous.hash == data_version.hash) {
+            self.data_versions
+                .lock()
+                .unwrap()
+                .insert(version_key, data_version);
+            return Ok(None);
         }
-    });
-}
 
-fn spawn_file_watcher(
-    rx: mpsc::Receiver<Result<Event>>,
-    tx: broadcast::Sender<DbBroadcastEvent>,
-    config: std::sync::Arc<config::RootConfig>,
-) {
-    tokio::task::spawn_blocking(move || {
-        for res in rx {
-            handle_file_watch_event(res, &config, tx.clone());
+        let inferred_schemas = self.check_source_schema(config, params)?;
+        let scanned = Self::scan_rejects(&tables)?;
+
+        // Replace the views and rejects of every table at once.
+        let conn = self.pool.write(cancel)?;
+        Self::create_source_views(&conn, config, params, &inferred_schemas)?;
+        let rejects = Self::record_rejects(&conn, config, scanned)?;
+        drop(conn);
+
+        self.data_versions
+            .lock()
+            .unwrap()
+            .insert(version_key, data_version);
+        if !config.params.is_empty() {
+            self.view_params
+                .lock()
+                .unwrap()
+                .insert(config.name.clone(), params_key);
         }
-    });
-}
+        Ok(Some(Refreshed { rejects, report }))
+    }
 
-fn handle_file_watch_event(
-    event: Result<Event>,
-    config: &config::RootConfig,
-    tx: broadcast::Sender<DbBroadcastEvent>,
-) {
-    let queries_to_watch = config
-        .queries
-        .clone()
-        .iter()
-        .filter(|q| q.source.path().is_some())
-        .map(|q| q.clone())
-        .collect::<Vec<_>>();
+    /// Find the records of each JSON table that `read_json` drops because of
+    /// `ignore_errors`.
+    fn scan_rejects(
+        tables: &[config::SourceTable],
+    ) -> anyhow::Result<Vec<(String, Vec<rejects::Reject>)>> {
+        tables
+            .iter()
+            .map(|table| {
+                let rejects = match table.format {
+                    config::DataFormat::Json => rejects::scan_json_file(&table.path)?,
+                    _ => vec![],
+                };
+                Ok((table.name.clone(), rejects))
+            })
+            .collect()
+    }
 
-    tokio::spawn(async move {
-        match event {
-            Ok(Event {
-                kind: EventKind::Modify(ModifyKind::Data(_)),
-                paths,
-                ..
-            }) => {
-                for path in paths {
-                    for query in queries_to_watch.iter() {
-                        if query.source.path().is_some()
-                            && query.source.path().unwrap().canonicalize()?
-                                == path.canonicalize()?
-                        {
-                            let _ = tx.send(DbBroadcastEvent::QueryUpdated {
-                                name: query.name.clone(),
-                            });
-                        }
-                    }
+    /// Record the rejected records of each table, and expose them as a
+    /// `<table>__rejects` view.
+    fn record_rejects(
+        conn: &Connection,
+        config: &config::Data,
+        scanned: Vec<(String, Vec<rejects::Reject>)>,
+    ) -> anyhow::Result<Vec<(String, u64)>> {
+        let mut rejects_counts = vec![];
+
+        for (table_name, rejects) in scanned {
+            let rejects_table_name = format!("{}__rejects", table_name);
+            validate_table_name(&rejects_table_name)?;
+
+            conn.execute(
+                "DELETE FROM frogtable_rejects WHERE source_table = ?;",
+                params![table_name],
+            )?;
+            let mut stmt = conn.prepare("INSERT INTO frogtable_rejects VALUES (?, ?, ?, ?);")?;
+            for reject in rejects.iter() {
+                stmt.execute(params![table_name, reject.line, reject.error, reject.raw])?;
+            }
+            conn.execute(
+                &format!(
+                    "CREATE OR REPLACE VIEW {} AS SELECT line, error, raw FROM frogtable_rejects WHERE source_table = '{}';",
+                    escape_table_name(&rejects_table_name),
+                    table_name,
+                ),
+                params![],
+            )?;
+
+            if !rejects.is_empty() {
+                eprintln!(
+                    "[Refreshing {}]: {} malformed record(s) rejected, see `{}`",
+                    config.name,
+                    rejects.len(),
+                    rejects_table_name
+                );
+                rejects_counts.push((rejects_table_name, rejects.len() as u64));
+            }
+        }
+
+        Ok(rejects_counts)
+    }
+
+    pub fn list_sources(&self) -> anyhow::Result<Vec<SourceStatus>> {
+        let statuses = self.statuses.lock().unwrap();
+        Ok(self
+            .config
+            .sources
+            .iter()
+            .map(|config| {
+                statuses
+                    .get(&config.name)
+                    .cloned()
+                    .unwrap_or_else(|| SourceStatus::new(&config.name))
+            })
+            .collect())
+    }
+
+    /// Warnings about the sources a query depends on, to be shown alongside
+    /// its results.
+    pub fn source_warnings(&self, query_name: &str) -> anyhow::Result<Vec<String>> {
+        let statuses = self.statuses.lock().unwrap();
+        let mut warnings = vec![];
+        for config in self.find_dependent_sources(query_name)? {
+            if let Some(status) = statuses.get(&config.name) {
+                if status.rejects_count > 0 {
+                    let views = status
+                        .rejects_views
+                        .iter()
+                        .map(|view| format!("`{}`", view))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    warnings.push(format!(
+                        "Source `{}` rejected {} malformed record(s). Query {} for details.",
+                        config.name, status.rejects_count, views
+                    ));
+                }
+                if status.unmatched_lines > 0 {
+                    warnings.push(format!(
+                        "Source `{}` has {} line(s) that did not match its pattern. They have `_raw` set.",
+                        config.name, status.unmatched_lines
+                    ));
                 }
             }
-            Ok(_) => {}
-            Err(e) => eprintln!("watch error: {:?}", e),
+        }
+        Ok(warnings)
+    }
+
+    pub fn exec_query(
+        &self,
+        name: &str,
+        options: &ExecOptions,
+        cancel: &CancelToken,
+    ) -> anyhow::Result<ExecQueryResult> {
+        let started_at = std::time::Instant::now();
+        let result = self.cached_query(name, options, cancel);
+        self.record_run(&graph::query_id(name), started_at, result.as_ref().err());
+        result
+    }
+
+    /// Serve a page from the result cache, or run the query and cache it.
+    fn cached_query(
+        &self,
+        name: &str,
+        options: &ExecOptions,
+        cancel: &CancelToken,
+    ) -> anyhow::Result<ExecQueryResult> {
+        let version = self.result_version(name, &options.params, cancel)?;
+        let key = options.cache_key(name)?;
+        let cached = self.results.lock().unwrap().get(key, version);
+        let mut result = match cached {
+            Some(mut result) => {
+                result.from_cache = true;
+                result
+            }
+            None => {
+                let mut result = self.run_query(name, options, cancel)?;
+                result.etag = etag(version, key);
+                // The data may have changed while the query ran.
+                if self.result_version(name, &options.params, cancel)? == version {
+                    self.results.lock().unwrap().insert(
+                        key,
+                        name,
+                        &options.params,
+                        version,
+                        result.clone(),
+                    );
+                }
+                result
+            }
         };
 
-        Ok::<(), anyhow::Error>(())
-    });
+        if matches!(options.count, CountMode::Deferred) {
+            let token = options.count_token(name, version)?;
+            result.total_count = self.deferred_count(name, options, &token);
+            result.count_token = Some(token);
+        }
+        Ok(result)
+    }
+
+    /// The count of rows identified by `token`, if it is known yet.
+    /// Otherwise, count them in the background, then broadcast the count and
+    /// keep it for `total_count`.
+    fn deferred_count(&self, name: &str, options: &ExecOptions, token: &str) -> Option<u32> {
+        let mut counts = self.counts.lock().unwrap();
+        if let Some(count) = counts.get(token) {
+            return *count;
+        }
+        if counts.len() >= MAX_DEFERRED_COUNTS {
+            counts.retain(|_, count| count.is_none());
+        }
+        counts.insert(token.to_string(), None);
+        drop(counts);
 
-    ()
+        let db = self.clone();
+        let name = name.to_string();
+        let token = token.to_string();
+        let options = options.clone();
+        tokio::task::spawn_blocking(move || match db.leased_count_rows(&name, &options) {
+            Ok(total_count) => {
+                db.counts
+                    .lock()
+                    .unwrap()
+                    .insert(token.clone(), Some(total_count));
+                let _ = db.tx.send(DbBroadcastEvent::TotalCount {
+                    name,
+                    count_token: token,
+                    total_count,
+                });
+            }
+            Err(e) => {
+                eprintln!("Error counting the rows of `{}`: {}", name, e);
+                db.counts.lock().unwrap().remove(&token);
+            }
+        });
+        None
+    }
+
+    /// The count of rows a deferred count identified by `token` found, or
+    /// `None` while it is still running.
+    pub fn total_count(&self, token: &str) -> anyhow::Result<Option<u32>> {
+        self.counts
+            .lock()
+            .unwrap()
+            .get(token)
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("Unknown count token. Run the query again."))
+    }
+
+    /// Count the rows in the background, once the parameterized sources are
+    /// back on the values of `options` if another request moved them.
+    fn leased_count_rows(&self, name: &str, options: &ExecOptions) -> anyhow::Result<u32> {
+        let _leases = self.lease_query_sources(name, &options.params, &CancelToken::default())?;
+        self.count_rows(name, options)
+    }
+
+    fn count_rows(&self, name: &str, options: &ExecOptions) -> anyhow::Result<u32> {
+        let query = self.find_query(name)?;
+        let (relation, _, conditions, params) =
+            self.filtered_relation(query, options, &CancelToken::default())?;
+        let conn = self.pool.read();
+        let count = conn
+            .prepare(&format!(
+                "SELECT COUNT(*) FROM {} {};",
+                relation,
+                where_sql(&conditions)
+            ))?
+            .query(duckdb::params_from_iter(params.iter()))?
+            .next()?
+            .unwrap()
+            .get(0)?;
+        Ok(count)
+    }
+
+    /// Statistics of each column of the rows of `name` matching the filters
+    /// of `options`, with the `top_k` most frequent values of each.
+    pub fn summarize_query(
+        &self,
+        name: &str,
+        options: &ExecOptions,
+        top_k: u32,
+        cancel: &CancelToken,
+    ) -> anyhow::Result<Vec<summary::ColumnSummary>> {
+        let query = self.find_query(name)?;
+        let (relation, columns) = self.relation(query, options, cancel)?;
+        let (conditions, params) = self.filter_conditions(
+            &columns,
+            options.filter.as_ref(),
+            options.raw_filter.as_deref(),
+        )?;
+        let rows_sql = format!("SELECT * FROM {} {}", relation, where_sql(&conditions));
+
+        self.cancellable_read(name, cancel, |conn| {
+            summarize_on(conn, &rows_sql, &params, &columns, top_k, cancel)
+        })
+    }
+
+    /// The distinct values of a column of `name` over the rows matching the
+    /// filters of `options`, with how many rows have each, and a histogram
+    /// for numeric and temporal columns.
+    pub fn column_values(
+        &self,
+        name: &str,
+        options: &ExecOptions,
+        values: &summary::ValuesOptions,
+        cancel: &CancelToken,
+    ) -> anyhow::Result<summary::ColumnValues> {
+        let query = self.find_query(name)?;
+        let (relation, columns) = self.relation(query, options, cancel)?;
+        let names = columns
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        let path = column_path(&values.column, &columns);
+        let column = column_path_sql(&path, &names)?;
+        let filter = match values.exclude_column_filter {
+            true => options
+                .filter
+                .as_ref()
+                .and_then(|filter| filter.without_path(&path)),
+            false => options.filter.clone(),
+        };
+        let (conditions, params) =
+            self.filter_conditions(&columns, filter.as_ref(), options.raw_filter.as_deref())?;
+        let rows_sql = format!(
+            "SELECT {} AS value FROM {} {}",
+            column,
+            relation,
+            where_sql(&conditions)
+        );
+        let data_type = self
+            .describe(&format!("SELECT {} FROM {}", column, relation))?
+            .remove(0)
+            .1;
+
+        self.cancellable_read(name, cancel, |conn| {
+            column_values_on(conn, &rows_sql, &params, values, &data_type, cancel)
+        })
+    }
+
+    /// What a request selects from: the view of `query`, or a projection of
+    /// it with the requested and computed columns. Returns its SQL and
+    /// columns.
+    fn relation(
+        &self,
+        query: &config::Query,
+        options: &ExecOptions,
+        cancel: &CancelToken,
+    ) -> anyhow::Result<(String, Columns)> {
+        let view = self.query_view(query, cancel)?;
+        let table = escape_table_name(&query.name);
+        if options.columns.is_none() && options.computed_columns.is_empty() {
+            return Ok((table, view.columns));
+        }
+        if !options.computed_columns.is_empty() && !self.config.allow_computed_columns {
+            return Err(anyhow::anyhow!(
+                "Computed columns are disabled. Start frogtable with --allow-computed-columns to enable them."
+            ));
+        }
+
+        let names = view
+            .columns
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        let selected = options.columns.as_ref().unwrap_or(&names);
+        let mut select = match &options.columns {
+            Some(columns) => columns
+                .iter()
+                .map(|column| column_path_sql(std::slice::from_ref(column), &names))
+                .collect::<anyhow::Result<Vec<_>>>()?,
+            None => vec!["*".to_string()],
+        };
+        for (i, computed) in options.computed_columns.iter().enumerate() {
+            let taken = selected
+                .iter()
+                .chain(options.computed_columns[..i].iter().map(|c| &c.name))
+                .any(|name| deps::normalize_name(name) == deps::normalize_name(&computed.name));
+            if taken {
+                return Err(anyhow::anyhow!(
+                    "Cannot add computed column `{}`: there is already a column with that name",
+                    computed.name
+                ));
+            }
+            select.push(format!(
+                "{} AS {}",
+                filter::validate_scalar_expression(&computed.expression)?,
+                escape_identifier(&computed.name)
+            ));
+        }
+        if select.is_empty() {
+            return Err(anyhow::anyhow!("Select at least one column"));
+        }
+
+        let relation = format!("(SELECT {} FROM {}) AS {}", select.join(", "), table, table);
+        let columns = self.describe(&format!("SELECT * FROM {}", relation))?;
+        Ok((relation, columns))
+    }
+
+    /// What a request pages through, with its columns, and the `WHERE`
+    /// conditions on it with the parameters they take. An aggregation is
+    /// computed over the rows matching the filters, so its groups are not
+    /// filtered any further.
+    fn filtered_relation(
+        &self,
+        query: &config::Query,
+        options: &ExecOptions,
+        cancel: &CancelToken,
+    ) -> anyhow::Result<(String, Columns, Vec<String>, Vec<duckdb::types::Value>)> {
+        let (relation, columns) = self.relation(query, options, cancel)?;
+        let (conditions, params) = self.filter_conditions(
+            &columns,
+            options.filter.as_ref(),
+            options.raw_filter.as_deref(),
+        )?;
+        match &options.aggregation {
+            Some(aggregation) => {
+                let (relation, columns) = self.aggregated_relation(
+                    query,
+                    &relation,
+                    &columns,
+                    &conditions,
+                    &params,
+                    aggregation,
+                )?;
+                Ok((relation, columns, vec![], params))
+            }
+            None => Ok((relation, columns, conditions, params)),
+        }
+    }
+
+    /// The groups of the rows of `relation` matching `conditions`, with their
+    /// aggregates. Returns its SQL and columns.
+    fn aggregated_relation(
+        &self,
+        query: &config::Query,
+        relation: &str,
+        columns: &Columns,
+        conditions: &[String],
+        params: &[duckdb::types::Value],
+        aggregation: &Aggregation,
+    ) -> anyhow::Result<(String, Columns)> {
+        let names = columns
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        let column_sql = |column: &str| column_path_sql(&column_path(column, columns), &names);
+        let where_clause = where_sql(conditions);
+
+        let mut select = vec![];
+        let mut output_names = vec![];
+        for column in aggregation.group_by.iter() {
+            select.push(format!(
+                "{} AS {}",
+                column_sql(column)?,
+                escape_identifier(column)
+            ));
+            output_names.push(column.clone());
+        }
+
+        // Pivoting computes each aggregate once per value of the pivot
+        // column, over the rows with that value.
+        let pivot_values = match &aggregation.pivot {
+            Some(pivot) => {
+                let pivot_sql = column_sql(pivot)?;
+                let conn = self.pool.read();
+                let values = conn
+                    .prepare(&format!(
+                        "SELECT CAST(value AS VARCHAR) FROM (SELECT DISTINCT {} AS value FROM {} {}) ORDER BY value NULLS LAST LIMIT {};",
+                        pivot_sql,
+                        relation,
+                        where_clause,
+                        aggregate::MAX_PIVOT_VALUES + 1
+                    ))?
+                    .query(duckdb::params_from_iter(params.iter()))?
+                    .map(|row| row.get::<_, Option<String>>(0))
+                    .collect::<Vec<_>>()?;
+                if values.len() > aggregate::MAX_PIVOT_VALUES {
+                    return Err(anyhow::anyhow!(
+                        "Cannot pivot on `{}`: it has more than {} distinct values",
+                        pivot,
+                        aggregate::MAX_PIVOT_VALUES
+                    ));
+                }
+                values
+                    .into_iter()
+                    .map(|value| match value {
+                        Some(value) => (
+                            format!("{}_", value),
+                            Some(format!(
+                                "CAST({} AS VARCHAR) = '{}'",
+                                pivot_sql,
+                                value.replace('\'', "''")
+                            )),
+                        ),
+                        None => ("NULL_".to_string(), Some(format!("{} IS NULL", pivot_sql))),
+                    })
+                    .collect()
+            }
+            None => vec![(String::new(), None)],
+        };
+        for (prefix, condition) in pivot_values.iter() {
+            for aggregate in aggregation.aggregates.iter() {
+                let column = aggregate.column.as_deref().map(column_sql).transpose()?;
+                let name = format!("{}{}", prefix, aggregate.name());
+                select.push(format!(
+                    "{} AS {}",
+                    aggregate.to_sql(column.as_deref(), condition.as_deref())?,
+                    escape_identifier(&name)
+                ));
+                output_names.push(name);
+            }
+        }
+        if select.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Group by at least one column or add an aggregate"
+            ));
+        }
+        for (i, name) in output_names.iter().enumerate() {
+            if output_names[..i]
+                .iter()
+                .any(|other| deps::normalize_name(other) == deps::normalize_name(name))
+            {
+                return Err(anyhow::anyhow!(
+                    "Duplicate column `{}` in the aggregation",
+                    name
+                ));
+            }
+        }
+
+        let group_by = match aggregation.group_by.len() {
+            0 => "".to_string(),
+            n => format!(
+                "GROUP BY {}",
+                (1..=n)
+                    .map(|i| i.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+        let columns = self.describe(&format!(
+            "SELECT {} FROM {} {}",
+            select.join(", "),
+            relation,
+            group_by
+        ))?;
+        let relation = format!(
+            "(SELECT {} FROM {} {} {}) AS {}",
+            select.join(", "),
+            relation,
+            where_clause,
+            group_by,
+            escape_table_name(&query.name)
+        );
+        Ok((relation, columns))
+    }
+
+    /// The `WHERE` conditions for a structured and a raw filter, with the
+    /// parameters they take.
+    fn filter_conditions(
+        &self,
+        columns: &Columns,
+        filter: Option<&Filter>,
+        raw_filter: Option<&str>,
+    ) -> anyhow::Result<(Vec<String>, Vec<duckdb::types::Value>)> {
+        let mut conditions = vec![];
+        let mut params: Vec<duckdb::types::Value> = vec![];
+        if let Some(filter) = filter {
+            let column_names = columns
+                .iter()
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<_>>();
+            conditions.push(filter.to_sql(&column_names, &mut params)?);
+        }
+        if let Some(raw_filter) = raw_filter.filter(|f| !f.trim().is_empty()) {
+            if !self.config.allow_raw_filters {
+                return Err(anyhow::anyhow!(
+                    "Raw SQL filters are disabled. Start frogtable with --allow-raw-filters to enable them."
+                ));
+            }
+            conditions.push(format!("({})", filter::validate_raw_filter(raw_filter)?));
+        }
+        Ok((conditions, params))
+    }
+
+    /// Identifies what the results of `name` are computed from: the SQL of
+    /// the query and of the queries it selects from, and the data of the
+    /// sources it reads.
+    fn result_version(
+        &self,
+        name: &str,
+        params: &config::Params,
+        cancel: &CancelToken,
+    ) -> anyhow::Result<u64> {
+        let view = self.query_view(self.find_query(name)?, cancel)?;
+        let data_versions = self.data_versions.lock().unwrap();
+        let mut hasher = std::hash::DefaultHasher::new();
+        view.sql_hash.hash(&mut hasher);
+        view.version.hash(&mut hasher);
+        for source in self.find_dependent_sources(name)? {
+            let params_key = source.params_key(params)?;
+            source.name.hash(&mut hasher);
+            params_key.hash(&mut hasher);
+            data_versions
+                .get(&(source.name, params_key))
+                .map(|version| version.hash)
+                .hash(&mut hasher);
+        }
+        Ok(hasher.finish())
+    }
+
+    /// The ETag of the page `options` fetch from `name`, which changes
+    /// whenever its results may have. Computing it does not run the query.
+    pub fn result_etag(
+        &self,
+        name: &str,
+        options: &ExecOptions,
+        cancel: &CancelToken,
+    ) -> anyhow::Result<String> {
+        Ok(etag(
+            self.result_version(name, &options.params, cancel)?,
+            options.cache_key(name)?,
+        ))
+    }
+
+    pub fn cache_stats(&self) -> CacheStats {
+        self.results.lock().unwrap().stats()
+    }
+
+    fn record_run(&self, id: &str, started_at: std::time::Instant, error: Option<&anyhow::Error>) {
+        self.run_statuses.lock().unwrap().insert(
+            id.to_string(),
+            RunStatus {
+                last_run_at: Some(chrono::Utc::now().to_rfc3339()),
+                last_run_ms: Some(started_at.elapsed().as_millis() as u64),
+                error: error.map(|e| e.to_string()),
+            },
+        );
+    }
+
+    fn run_query(
+        &self,
+        name: &str,
+        options: &ExecOptions,
+        cancel: &CancelToken,
+    ) -> anyhow::Result<ExecQueryResult> {
+        let ExecOptions {
+            page,
+            per_page,
+            order_by,
+            keyset,
+            cursor,
+            count,
+            ..
+        } = options;
+        let query = self.find_query(name)?;
+        let (relation, columns, conditions, params) =
+            self.filtered_relation(query, options, cancel)?;
+
+        let mut sort_keys = order_by
+            .iter()
+            .map(|ordering| ordering.sort_key(&columns))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        // Paging by cursor needs every row to have a well-defined position.
+        let keyset = *keyset || cursor.is_some();
+        if keyset {
+            // Groups are unique by their columns, whatever the key columns
+            // of the rows.
+            let key_columns = match options.aggregation {
+                Some(_) => &[][..],
+                None => &query.key_columns[..],
+            };
+            let tiebreakers = tiebreaker_keys(key_columns, &columns, &sort_keys)?;
+            sort_keys.extend(tiebreakers);
+        }
+        let cursor = cursor
+            .as_deref()
+            .map(|cursor| Cursor::decode(cursor, &sort_keys))
+            .transpose()?;
+        let backward = cursor.as_ref().is_some_and(|cursor| cursor.backward);
+
+        let order_clause = if !sort_keys.is_empty() {
+            format!("ORDER BY {}", cursor::order_by_sql(&sort_keys, backward))
+        } else {
+            "".to_string()
+        };
+
+        let where_clause = where_sql(&conditions);
+
+        // Only the page is restricted to the rows past the cursor, not the
+        // count.
+        let mut page_conditions = conditions.clone();
+        let mut page_params = params.clone();
+        if let Some(cursor) = &cursor {
+            // The cursor only holds values, which are cast to the types of
+            // their keys as described here.
+            let key_types = self
+                .describe(&format!(
+                    "SELECT {} FROM {}",
+                    sort_keys
+                        .iter()
+                        .map(|key| key.sql.clone())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    relation
+                ))?
+                .into_iter()
+                .map(|(_, data_type)| data_type)
+                .collect::<Vec<_>>();
+            page_conditions.push(cursor.to_sql(&sort_keys, &key_types, &mut page_params));
+        }
+        let page_where_clause = where_sql(&page_conditions);
+
+        // By cursor, one row more than the page tells whether there are more
+        // pages, and the page also selects the value of each sort key to
+        // build the cursors from.
+        let (limit, offset) = match keyset {
+            true => (per_page + 1, 0),
+            false => (*per_page, (page - 1) * per_page),
+        };
+        let key_columns = match keyset {
+            true => sort_keys
+                .iter()
+                .enumerate()
+                .map(|(i, key)| {
+                    format!(
+                        ", CAST(({}) AS VARCHAR) AS \"__frogtable_key_{}\"",
+                        key.sql, i
+                    )
+                })
+                .collect::<String>(),
+            false => "".to_string(),
+        };
+        let exact_count = matches!(count, CountMode::Exact);
+        let hidden_columns = match keyset {
+            true => sort_keys.len(),
+            false => 0,
+        } + usize::from(exact_count);
+
+        let page_sql = format!(
+            "SELECT *{} FROM {} {} {} LIMIT {} OFFSET {}",
+            key_columns, relation, page_where_clause, order_clause, limit, offset,
+        );
+        let count_sql = format!(
+            "SELECT COUNT(*) AS count FROM {} {}",
+            relation, where_clause
+        );
+        // The total count comes with the page in a single statement. The
+        // page is selected in a subquery of its own so that DuckDB can still
+        // use a top-N sort for it; a `COUNT(*) OVER ()` window would force
+        // every row to be materialized instead.
+        let wrapped_sql = match exact_count {
+            true => format!(
+                "SELECT page.*, total.count AS {} FROM ({}) AS page, ({}) AS total;",
+                TOTAL_COUNT_COLUMN, page_sql, count_sql,
+            ),
+            false => format!("{};", page_sql),
+        };
+        let wrapped_params = match exact_count {
+            true => page_params.iter().chain(params.iter()).collect::<Vec<_>>(),
+            false => page_params.iter().collect(),
+        };
+
+        self.cancellable_read(name, cancel, |conn| {
+            let mut stmt: duckdb::Statement<'_> = conn.prepare(&wrapped_sql)?;
+            // The filter's parameters appear twice: in the page, then in the
+            // count.
+            let rows = stmt.query(duckdb::params_from_iter(wrapped_params))?;
+            let mut results = rows.map(json::duckdb_row_to_json).collect::<Vec<_>>()?;
+
+            let mut total_count = None;
+            let mut row_keys = vec![];
+            for row in results.iter_mut() {
+                if exact_count {
+                    total_count = row.pop().and_then(|count| count.as_u64());
+                }
+                let visible_columns = row.len() + usize::from(exact_count) - hidden_columns;
+                row_keys.push(row.split_off(visible_columns));
+            }
+
+            let mut next_cursor = None;
+            let mut prev_cursor = None;
+            if keyset {
+                let has_more = results.len() > *per_page as usize;
+                results.truncate(*per_page as usize);
+                row_keys.truncate(*per_page as usize);
+                if backward {
+                    results.reverse();
+                    row_keys.reverse();
+                }
+                let cursor_at = |keys: &Vec<serde_json::Value>, backward: bool| {
+                    let values = keys
+                        .iter()
+                        .map(|value| value.as_str().map(str::to_string))
+                        .collect();
+                    Cursor::new(&sort_keys, backward, values).encode()
+                };
+                // Coming back from a later page, there are rows after this
+                // one; coming from an earlier one, there are rows before.
+                let (more_after, more_before) = match backward {
+                    true => (true, has_more),
+                    false => (has_more, cursor.is_some()),
+                };
+                if more_after {
+                    next_cursor = row_keys.last().map(|keys| cursor_at(keys, false));
+                }
+                if more_before {
+                    prev_cursor = row_keys.first().map(|keys| cursor_at(keys, true));
+                }
+            }
+
+            drop(stmt);
+
+            let total_count = match (count, total_count) {
+                (CountMode::Exact, Some(total_count)) => Some(total_count as u32),
+                // A page past the end has no rows to carry the count.
+                (CountMode::Exact, None) => {
+                    cancel.check()?;
+                    Some(
+                        conn.prepare(&format!("{};", count_sql))?
+                            .query(duckdb::params_from_iter(params.iter()))?
+                            .next()?
+                            .unwrap()
+                            .get(0)?,
+                    )
+                }
+                (CountMode::Approximate, _) => {
+                    cancel.check()?;
+                    Some(estimate_rows(
+                        conn,
+                        &format!("SELECT * FROM {} {}", relation, where_clause),
+                        &params,
+                    )?)
+                }
+                (CountMode::Deferred, _) => None,
+            };
+
+            Ok(ExecQueryResult {
+                total_count,
+                total_count_estimated: matches!(count, CountMode::Approximate),
+                count_token: None,
+                data: results,
+                schema: columns
+                    .iter()
+                    .map(|(name, data_type)| schema::Field {
+                        name: name.clone(),
+                        column_type: schema::ColumnType::parse(data_type),
+                    })
+                    .collect(),
+                next_cursor,
+                prev_cursor,
+                etag: String::new(),
+                from_cache: false,
+            })
+        })
+    }
+
+    /// Run `f` on a read connection, interrupting it if `cancel` is
+    /// cancelled while it runs.
+    fn cancellable_read<T>(
+        &self,
+        name: &str,
+        cancel: &CancelToken,
+        f: impl FnOnce(&Connection) -> anyhow::Result<T>,
+    ) -> anyhow::Result<T> {
+        let conn = self.pool.read();
+        cancel.attach(&conn)?;
+        let result = f(&conn);
+        cancel.detach();
+
+        // DuckDB reports an interrupted query as a generic failure.
+        match result {
+            Err(_) if cancel.is_cancelled() => {
+                Err(anyhow::anyhow!("Query `{}` was cancelled", name))
+            }
+            result => result,
+        }
+    }
+
+    /// How long `query_name` may run before it is cancelled: its own timeout,
+    /// or the global one.
+    pub fn query_timeout(&self, query_name: &str) -> Option<std::time::Duration> {
+        self.find_query(query_name)
+            .ok()
+            .and_then(|query| query.timeout_secs)
+            .or(self.config.query_timeout_secs)
+            .map(std::time::Duration::from_secs)
+    }
+
+    /// The sources a query reads from, either directly or through the views
+    /// of other queries it references.
+    pub fn find_dependent_sources(&self, query_name: &str) -> anyhow::Result<Vec<config::Data>> {
+        let mut dependent_sources: Vec<config::Data> = vec![];
+        let mut visited = HashSet::new();
+        let mut pending = vec![query_name.to_string()];
+
+        while let Some(name) = pending.pop() {
+            if !visited.insert(deps::normalize_name(&name)) {
+                continue;
+            }
+
+            let query = self.find_query(&name)?;
+
+            let referenced_tables = deps::referenced_tables(&query.source.sql()?);
+
+            for other_query in self.config.queries.iter() {
+                if referenced_tables.contains(&deps::normalize_name(&other_query.name)) {
+                    pending.push(other_query.name.clone());
+                }
+            }
+
+            for data_source in self.config.sources.iter() {
+                if dependent_sources.iter().any(|s| s.name == data_source.name) {
+                    continue;
+                }
+                let table_names = data_source.table_names()?;
+                if table_names
+                    .iter()
+                    .any(|name| referenced_tables.contains(&deps::normalize_name(name)))
+                {
+                    dependent_sources.push(data_source.clone());
+                }
+            }
+        }
+
+        Ok(dependent_sources)
+    }
+
+    pub fn list_queries(&self) -> anyhow::Result<Vec<config::Query>> {
+        Ok(self.config.queries.clone())
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct ExecQueryResult {
+    pub total_count: Option<u32>,
+    /// Whether `total_count` is DuckDB's estimate rather than an exact count.
+    pub total_count_estimated: bool,
+    /// Identifies the deferred count of the rows.
+    pub count_token: Option<String>,
+    pub data: Vec<Vec<serde_json::Value>>,
+    pub schema: Vec<schema::Field>,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
+    pub etag: String,
+    pub from_cache: bool,
+}
+
+/// What a refresh that replaced the views of a source found.
+struct Refreshed {
+    /// The number of rejected records in each rejects view that has any.
+    rejects: Vec<(String, u64)>,
+    report: config::RefreshReport,
+}
+
+#[derive(TS, Serialize, Deserialize, Debug, Clone)]
+pub struct SourceStatus {
+    pub name: String,
+    /// RFC 3339 timestamp of the last refresh attempt.
+    pub last_refreshed_at: Option<String>,
+    pub last_refresh_ms: Option<u64>,
+    /// The error of the last refresh, if it failed.
+    pub error: Option<String>,
+    /// Number of malformed records dropped during the last successful
+    /// refresh.
+    pub rejects_count: u64,
+    /// The `<table>__rejects` views holding them, for the tables of the source
+    /// that had any.
+    pub rejects_views: Vec<String>,
+    /// Number of lines of a text source that did not match its pattern during
+    /// the last successful refresh. They are kept with only `_line` and
+    /// `_raw` set.
+    pub unmatched_lines: u64,
+}
+
+impl SourceStatus {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            last_refreshed_at: None,
+            last_refresh_ms: None,
+            error: None,
+            rejects_count: 0,
+            rejects_views: vec![],
+            unmatched_lines: 0,
+        }
+    }
+}
+
+/// What the view of a query was last created from, so that it is only
+/// replaced when that changes.
+#[derive(Debug, Clone)]
+struct ViewState {
+    sql_hash: u64,
+    /// Modification time of the SQL file, which saves reading it again.
+    modified: Option<std::time::SystemTime>,
+    /// Distinguishes each (re)creation of a view.
+    version: u64,
+    /// The query views this one selects from, with the version each had
+    /// when this view was created.
+    upstream: Vec<(String, u64)>,
+    columns: Columns,
+}
+
+/// How the total number of rows matching the filters is counted.
+#[derive(TS, Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub enum CountMode {
+    /// Count every row along with the page.
+    #[default]
+    Exact,
+    /// Use DuckDB's estimate, which is cheap but may be far off.
+    Approximate,
+    /// Return the page without a count, and count in the background. The
+    /// count is broadcast as a `TotalCount` event, and can also be fetched
+    /// with its `count_token`.
+    Deferred,
+}
+
+/// Deferred counts kept for `total_count`. Finished ones are dropped when
+/// there are more.
+const MAX_DEFERRED_COUNTS: usize = 1024;
+
+/// Extra column of a page of results, holding the total number of rows.
+const TOTAL_COUNT_COLUMN: &str = "\"__frogtable_total_count\"";
+
+static VIEW_VERSION: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+/// Which page of a query's results to fetch, and how.
+#[derive(Clone, Default)]
+pub struct ExecOptions {
+    pub page: u32,
+    pub per_page: u32,
+    pub order_by: Vec<Ordering>,
+    pub filter: Option<Filter>,
+    /// Only accepted when the server allows raw filters.
+    pub raw_filter: Option<String>,
+    /// Page by cursor rather than by page number.
+    pub keyset: bool,
+    /// Where to continue from, in keyset mode.
+    pub cursor: Option<String>,
+    pub count: CountMode,
+    /// The columns to return, in order. All of them if `None`.
+    pub columns: Option<Vec<String>>,
+    /// Columns to add after the others. Filters and orderings can refer to
+    /// them.
+    pub computed_columns: Vec<ComputedColumn>,
+    /// Page through groups of the rows matching the filters instead of the
+    /// rows. Orderings then refer to the columns of the groups.
+    pub aggregation: Option<Aggregation>,
+    /// Values of the parameters of the sources the query reads.
+    pub params: config::Params,
+}
+
+impl ExecOptions {
+    /// Identifies the page of `name` these options fetch, in the result
+    /// cache.
+    fn cache_key(&self, name: &str) -> anyhow::Result<u64> {
+        let mut hasher = std::hash::DefaultHasher::new();
+        name.hash(&mut hasher);
+        self.page.hash(&mut hasher);
+        self.per_page.hash(&mut hasher);
+        serde_json::to_string(&self.order_by)?.hash(&mut hasher);
+        serde_json::to_string(&self.filter)?.hash(&mut hasher);
+        self.raw_filter.hash(&mut hasher);
+        self.keyset.hash(&mut hasher);
+        self.cursor.hash(&mut hasher);
+        serde_json::to_string(&self.count)?.hash(&mut hasher);
+        self.columns.hash(&mut hasher);
+        serde_json::to_string(&self.computed_columns)?.hash(&mut hasher);
+        serde_json::to_string(&self.aggregation)?.hash(&mut hasher);
+        self.params.hash(&mut hasher);
+        Ok(hasher.finish())
+    }
+
+    /// Identifies the count of the rows of `name` matching the filters, as of
+    /// `version` of its results.
+    fn count_token(&self, name: &str, version: u64) -> anyhow::Result<String> {
+        let mut hasher = std::hash::DefaultHasher::new();
+        name.hash(&mut hasher);
+        version.hash(&mut hasher);
+        serde_json::to_string(&self.filter)?.hash(&mut hasher);
+        self.raw_filter.hash(&mut hasher);
+        self.columns.hash(&mut hasher);
+        serde_json::to_string(&self.computed_columns)?.hash(&mut hasher);
+        serde_json::to_string(&self.aggregation)?.hash(&mut hasher);
+        self.params.hash(&mut hasher);
+        Ok(format!("{:016x}", hasher.finish()))
+    }
+}
+
+#[derive(Default)]
+struct CancelState {
+    cancelled: bool,
+    /// Interrupts the connection the query is running on, while it runs.
+    interrupt: Option<std::sync::Arc<duckdb::InterruptHandle>>,
+}
+
+/// Lets a query be cancelled from another thread, e.g. when the HTTP request
+/// that started it is dropped.
+#[derive(Default)]
+pub struct CancelToken {
+    state: std::sync::Mutex<CancelState>,
+}
+
+impl CancelToken {
+    /// Cancel the query, interrupting DuckDB if it is running.
+    pub fn cancel(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.cancelled = true;
+        if let Some(interrupt) = &state.interrupt {
+            interrupt.interrupt();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.lock().unwrap().cancelled
+    }
+
+    fn check(&self) -> anyhow::Result<()> {
+        if self.is_cancelled() {
+            return Err(anyhow::anyhow!("Query was cancelled"));
+        }
+        Ok(())
+    }
+
+    /// Start running on `conn`. Fails if the query was already cancelled,
+    /// e.g. while it waited for the connection.
+    fn attach(&self, conn: &Connection) -> anyhow::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.cancelled {
+            return Err(anyhow::anyhow!("Query was cancelled"));
+        }
+        state.interrupt = Some(conn.interrupt_handle());
+        Ok(())
+    }
+
+    fn detach(&self) {
+        self.state.lock().unwrap().interrupt = None;
+    }
+}
+
+#[derive(TS, Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RunStatus {
+    /// RFC 3339 timestamp of the last run.
+    pub last_run_at: Option<String>,
+    pub last_run_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+#[derive(TS, Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "eventType")]
+#[ts(export)]
+pub enum DbBroadcastEvent {
+    Ping {
+        data: String,
+    },
+    QueryUpdated {
+        name: String,
+    },
+    /// The inferred schema of a source table differs from its previous
+    /// refresh.
+    SchemaChanged {
+        name: String,
+        columns: Vec<ColumnChange>,
+    },
+    /// A deferred count finished.
+    TotalCount {
+        name: String,
+        count_token: String,
+        total_count: u32,
+    },
+}
+
+/// A column that was added, removed or changed type. `before` is `None` for
+/// added columns and `after` is `None` for removed ones.
+#[derive(TS, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ColumnChange {
+    pub column: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+impl std::fmt::Display for ColumnChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.before, &self.after) {
+            (None, Some(after)) => write!(f, "`{}` added as {}", self.column, after),
+            (Some(before), None) => write!(f, "`{}` ({}) removed", self.column, before),
+            (Some(before), Some(after)) => {
+                write!(f, "`{}` changed from {} to {}", self.column, before, after)
+            }
+            (None, None) => write!(f, "`{}`", self.column),
+        }
+    }
+}
+
+fn diff_columns(before: &[(String, String)], after: &[(String, String)]) -> Vec<ColumnChange> {
+    let mut changes = vec![];
+    for (name, before_type) in before.iter() {
+        let after_type = after.iter().find(|(n, _)| n == name).map(|(_, t)| t);
+        if after_type != Some(before_type) {
+            changes.push(ColumnChange {
+                column: name.clone(),
+                before: Some(before_type.clone()),
+                after: after_type.cloned(),
+            });
+        }
+    }
+    for (name, after_type) in after.iter() {
+        if !before.iter().any(|(n, _)| n == name) {
+            changes.push(ColumnChange {
+                column: name.clone(),
+                before: None,
+                after: Some(after_type.clone()),
+            });
+        }
+    }
+    changes
+}
+
+/// The table function call that reads the (refreshed) contents of a source
+/// table.
+fn read_source_sql(table: &config::SourceTable) -> String {
+    let path = &table.path;
+    match table.format {
+        config::DataFormat::Json => format!(
+            "read_json('{}', ignore_errors = true, format = 'unstructured')",
+            path.display()
+        ),
+        config::DataFormat::Csv => format!("read_csv('{}')", path.display()),
+        config::DataFormat::Parquet => format!("read_parquet('{}')", path.display()),
+    }
+}
+
+/// The query behind a source table's view, applying the declared column
+/// overrides to the columns that are actually present.
+fn source_view_sql(
+    config: &config::Data,
+    table: &config::SourceTable,
+    inferred_columns: &[(String, String)],
+) -> anyhow::Result<String> {
+    // Casts fail loudly in strict mode; otherwise bad values become NULL.
+    let cast = if config.schema.strict {
+        "CAST"
+    } else {
+        "TRY_CAST"
+    };
+
+    let mut replace = vec![];
+    let mut rename = vec![];
+    for column in config.schema.columns.iter() {
+        if !inferred_columns
+            .iter()
+            .any(|(name, _)| name == &column.name)
+        {
+            continue;
+        }
+        let escaped_column = escape_identifier(&column.name);
+        if let Some(data_type) = &column.data_type {
+            validate_type_name(data_type)?;
+            replace.push(format!(
+                "{}({} AS {}) AS {}",
+                cast, escaped_column, data_type, escaped_column
+            ));
+        }
+        if let Some(new_name) = &column.rename {
+            rename.push(format!(
+                "{} AS {}",
+                escaped_column,
+                escape_identifier(new_name)
+            ));
+        }
+    }
+
+    let mut projection = "*".to_string();
+    if !replace.is_empty() {
+        projection.push_str(&format!(" REPLACE ({})", replace.join(", ")));
+    }
+    if !rename.is_empty() {
+        projection.push_str(&format!(" RENAME ({})", rename.join(", ")));
+    }
+
+    Ok(format!(
+        "SELECT {} FROM {}",
+        projection,
+        read_source_sql(table)
+    ))
+}
+
+fn escape_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn validate_type_name(name: &str) -> anyhow::Result<()> {
+    if !name
+        .chars()
+        .all(|c| c.is_alphanumeric() || " _(),[]".contains(c))
+    {
+        return Err(anyhow::anyhow!("Invalid type name: {}", name));
+    }
+    Ok(())
+}
+
+// I tried format_sql_query crate but it does not add quotes if hyphens are
+// present.
+fn escape_table_name(name: &str) -> String {
+    format!("\"{}\"", name)
+}
+
+pub(crate) fn validate_table_name(name: &str) -> anyhow::Result<()> {
+    // name should only contain a-zA-Z0-9-_
+    if !name
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(anyhow::anyhow!("Invalid table name: {}", name));
+    }
+    Ok(())
+}
+
+/// SQL for a column of a query result, or a field nested in a struct column
+/// when `path` has more than one element. The column must be one of
+/// `columns`, which guards against injection on top of the escaping.
+pub fn column_path_sql(path: &[String], columns: &[String]) -> anyhow::Result<String> {
+    let Some((column, fields)) = path.split_first() else {
+        return Err(anyhow::anyhow!("Empty column path"));
+    };
+    let column = columns
+        .iter()
+        .find(|c| deps::normalize_name(c) == deps::normalize_name(column))
+        .ok_or_else(|| anyhow::anyhow!("Unknown column `{}`", column))?;
+
+    let mut sql = escape_identifier(column);
+    for field in fields {
+        sql = format!("{}['{}']", sql, field.replace('\'', "''"));
+    }
+    Ok(sql)
+}
+
+/// The path `column_path_sql` takes for `column`: the column itself, or a
+/// dotted path into a struct column. A column whose name contains dots takes
+/// precedence.
+fn column_path(column: &str, columns: &Columns) -> Vec<String> {
+    match columns
+        .iter()
+        .any(|(name, _)| deps::normalize_name(name) == deps::normalize_name(column))
+    {
+        true => vec![column.to_string()],
+        false => column.split('.').map(|s| s.to_string()).collect(),
+    }
+}
+
+/// A column computed for a single request.
+#[derive(TS, Serialize, Deserialize, Debug, Clone)]
+pub struct ComputedColumn {
+    pub name: String,
+    /// A scalar SQL expression over the columns of the query, such as
+    /// `price * qty`.
+    pub expression: String,
+}
+
+#[derive(TS, Serialize, Deserialize, Debug, Clone)]
+pub struct Ordering {
+    /// A column of the query, or a dotted path into a struct column such as
+    /// `address.city`. A column whose name contains dots takes precedence.
+    column: String,
+    direction: Direction,
+    /// Where NULLs go. Defaults to DuckDB's `NULLS LAST`.
+    #[serde(default)]
+    nulls: Option<Nulls>,
+    /// Compare text case-insensitively.
+    #[serde(default)]
+    case_insensitive: bool,
+}
+
+impl Ordering {
+    pub fn sort_key(&self, columns: &Columns) -> anyhow::Result<SortKey> {
+        let names = columns
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        let column_type = columns
+            .iter()
+            .find(|(name, _)| deps::normalize_name(name) == deps::normalize_name(&self.column))
+            .map(|(_, data_type)| data_type);
+
+        let mut sql = column_path_sql(&column_path(&self.column, columns), &names)?;
+
+        if self.case_insensitive {
+            // Collations only apply to text. The type of a nested field is
+            // left for DuckDB to check.
+            if column_type.is_some_and(|data_type| data_type != "VARCHAR") {
+                return Err(anyhow::anyhow!(
+                    "Cannot order `{}` case-insensitively: it is not a text column",
+                    self.column
+                ));
+            }
+            sql = format!("{} COLLATE NOCASE", sql);
+        }
+
+        Ok(SortKey {
+            sql,
+            descending: matches!(self.direction, Direction::Desc),
+            nulls_first: matches!(self.nulls, Some(Nulls::First)),
+        })
+    }
+}
+
+#[derive(TS, Serialize, Deserialize, Debug, Clone, Copy)]
+pub enum Direction {
+    Asc,
+    Desc,
+}
+
+#[derive(TS, Serialize, Deserialize, Debug, Clone, Copy)]
+pub enum Nulls {
+    First,
+    Last,
+}
+
+/// Identifies the data of a source as of a refresh.
+#[derive(Debug, Clone, Copy)]
+struct DataVersion {
+    /// Modification times and sizes of the files, which tell cheaply that
+    /// they were not touched.
+    stat: u64,
+    /// Hash of the contents of the files.
+    hash: u64,
+}
+
+/// Hash the modification times and sizes of the files of `tables`.
+fn stat_tables(tables: &[config::SourceTable]) -> anyhow::Result<u64> {
+    let mut hasher = std::hash::DefaultHasher::new();
+    for table in tables {
+        let metadata = std::fs::metadata(&table.path)?;
+        table.name.hash(&mut hasher);
+        metadata.modified()?.hash(&mut hasher);
+        metadata.len().hash(&mut hasher);
+    }
+    Ok(hasher.finish())
+}
+
+/// Hash the contents of the files of `tables`.
+fn hash_tables(tables: &[config::SourceTable]) -> anyhow::Result<u64> {
+    let mut hasher = std::hash::DefaultHasher::new();
+    let mut buf = vec![0; 64 * 1024];
+    for table in tables {
+        table.name.hash(&mut hasher);
+        let mut file = std::fs::File::open(&table.path)?;
+        loop {
+            let n = std::io::Read::read(&mut file, &mut buf)?;
+            if n == 0 {
+                break;
+            }
+            hasher.write(&buf[..n]);
+        }
+    }
+    Ok(hasher.finish())
+}
+
+/// DuckDB's estimate of the number of rows `sql` returns, taken from its
+/// plan.
+fn estimate_rows(
+    conn: &Connection,
+    sql: &str,
+    params: &[duckdb::types::Value],
+) -> anyhow::Result<u32> {
+    let plan: String = conn
+        .prepare(&format!("EXPLAIN (FORMAT json) {};", sql))?
+        .query(duckdb::params_from_iter(params.iter()))?
+        .next()?
+        .ok_or_else(|| anyhow::anyhow!("EXPLAIN returned no plan"))?
+        .get(1)?;
+    let plan: serde_json::Value = serde_json::from_str(&plan)?;
+    // Operators that do not change the number of rows may not estimate it,
+    // so look down to the first one that does.
+    let mut node = &plan[0];
+    while !node.is_null() {
+        if let Some(estimate) = node["extra_info"]["Estimated Cardinality"].as_str() {
+            return Ok(estimate.parse()?);
+        }
+        node = &node["children"][0];
+    }
+    Err(anyhow::anyhow!(
+        "DuckDB did not estimate the number of rows"
+    ))
+}
+
+/// Summarize the columns of the rows selected by `rows_sql`, with the `top_k`
+/// most common values of each.
+fn summarize_on(
+    conn: &Connection,
+    rows_sql: &str,
+    params: &[duckdb::types::Value],
+    columns: &Columns,
+    top_k: u32,
+    cancel: &CancelToken,
+) -> anyhow::Result<Vec<summary::ColumnSummary>> {
+    let mut stmt = conn.prepare(&format!("SUMMARIZE {};", rows_sql))?;
+    let mut summaries = stmt
+        .query(duckdb::params_from_iter(params.iter()))?
+        .map(|row| {
+            let name: String = row.get("column_name")?;
+            let column_type = schema::ColumnType::parse(&row.get::<_, String>("column_type")?);
+            let mean = summary::parse_number(&column_type, row.get("avg")?);
+            let std = summary::parse_number(&column_type, row.get("std")?);
+            let quantiles = (|| {
+                Some(summary::Quantiles {
+                    q25: summary::parse_number(&column_type, row.get("q25").ok()?)?,
+                    q50: summary::parse_number(&column_type, row.get("q50").ok()?)?,
+                    q75: summary::parse_number(&column_type, row.get("q75").ok()?)?,
+                })
+            })();
+            Ok((
+                name.clone(),
+                summary::ColumnSummary {
+                    name,
+                    column_type,
+                    count: 0,
+                    null_count: 0,
+                    null_ratio: 0.0,
+                    approx_distinct: row.get("approx_unique")?,
+                    min: row.get("min")?,
+                    max: row.get("max")?,
+                    mean,
+                    std,
+                    quantiles,
+                    top_values: vec![],
+                },
+            ))
+        })
+        .collect::<HashMap<_, _>>()?;
+    drop(stmt);
+
+    // `SUMMARIZE` only gives a rounded percentage of nulls.
+    cancel.check()?;
+    let counts_sql = format!(
+        "SELECT COUNT(*), {} FROM ({});",
+        columns
+            .iter()
+            .map(|(name, _)| format!("COUNT({})", escape_identifier(name)))
+            .collect::<Vec<_>>()
+            .join(", "),
+        rows_sql
+    );
+    let mut stmt = conn.prepare(&counts_sql)?;
+    let mut rows = stmt.query(duckdb::params_from_iter(params.iter()))?;
+    let row = rows.next()?.unwrap();
+    let count: u64 = row.get(0)?;
+    let mut result = vec![];
+    for (i, (name, _)) in columns.iter().enumerate() {
+        let mut column_summary = summaries
+            .remove(name)
+            .ok_or_else(|| anyhow::anyhow!("Column `{}` was not summarized", name))?;
+        let non_null: u64 = row.get(i + 1)?;
+        column_summary.count = count;
+        column_summary.null_count = count - non_null;
+        if count > 0 {
+            column_summary.null_ratio = column_summary.null_count as f64 / count as f64;
+        }
+        result.push(column_summary);
+    }
+    drop(rows);
+    drop(stmt);
+
+    if top_k > 0 {
+        for column_summary in result.iter_mut() {
+            let column = escape_identifier(&column_summary.name);
+            let top_sql = format!(
+                "SELECT {}, COUNT(*) AS count FROM ({}) WHERE {} IS NOT NULL GROUP BY ALL ORDER BY count DESC, {} LIMIT {};",
+                column, rows_sql, column, column, top_k
+            );
+            cancel.check()?;
+            column_summary.top_values = conn
+                .prepare(&top_sql)?
+                .query(duckdb::params_from_iter(params.iter()))?
+                .map(|row| {
+                    Ok((
+                        row.get::<_, duckdb::types::Value>(0)?,
+                        row.get::<_, u64>(1)?,
+                    ))
+                })
+                .collect::<Vec<_>>()?
+                .into_iter()
+                .map(|(value, count)| {
+                    Ok(summary::ValueCount {
+                        value: json::duckdb_value_to_json_value(value)?,
+                        count,
+                    })
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+        }
+    }
+    Ok(result)
+}
+
+/// The distinct values of the `value` column of the rows selected by
+/// `rows_sql`, of type `data_type`, and their histogram.
+fn column_values_on(
+    conn: &Connection,
+    rows_sql: &str,
+    params: &[duckdb::types::Value],
+    values: &summary::ValuesOptions,
+    data_type: &str,
+    cancel: &CancelToken,
+) -> anyhow::Result<summary::ColumnValues> {
+    let column_type = schema::ColumnType::parse(data_type);
+    let mut values_params = params.to_vec();
+    let prefix_condition = match &values.prefix {
+        Some(prefix) => {
+            values_params.push(duckdb::types::Value::Text(prefix.clone()));
+            "WHERE starts_with(lower(CAST(value AS VARCHAR)), lower(?))"
+        }
+        None => "",
+    };
+    let mut counts = conn
+        .prepare(&format!(
+            "SELECT value, COUNT(*) AS count FROM ({}) {} GROUP BY value ORDER BY count DESC, value NULLS LAST LIMIT {};",
+            rows_sql,
+            prefix_condition,
+            values.limit as u64 + 1
+        ))?
+        .query(duckdb::params_from_iter(values_params.iter()))?
+        .map(|row| {
+            Ok((
+                row.get::<_, duckdb::types::Value>(0)?,
+                row.get::<_, u64>(1)?,
+            ))
+        })
+        .collect::<Vec<_>>()?;
+    let has_more = counts.len() > values.limit as usize;
+    counts.truncate(values.limit as usize);
+    let counts = counts
+        .into_iter()
+        .map(|(value, count)| {
+            Ok(summary::ValueCount {
+                value: json::duckdb_value_to_json_value(value)?,
+                count,
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    // Buckets are computed on numbers: temporal values are converted to
+    // microseconds since the epoch, and their bounds back.
+    let to_number = match &column_type {
+        column_type if column_type.is_numeric() => "CAST(value AS DOUBLE)",
+        schema::ColumnType::Date
+        | schema::ColumnType::Timestamp { .. }
+        | schema::ColumnType::Time {
+            with_time_zone: false,
+        } => "CAST(epoch_us(value) AS DOUBLE)",
+        _ => "",
+    };
+    let from_number = |number: &str| match &column_type {
+        schema::ColumnType::Date => {
+            format!("CAST(make_timestamp(CAST({} AS BIGINT)) AS DATE)", number)
+        }
+        schema::ColumnType::Timestamp {
+            time_zone: Some(_), ..
+        } => format!("to_timestamp(({}) / 1000000)", number),
+        schema::ColumnType::Timestamp { .. } => format!(
+            "CAST(make_timestamp(CAST({} AS BIGINT)) AS {})",
+            number, data_type
+        ),
+        schema::ColumnType::Time { .. } => format!(
+            "TIME '00:00:00' + to_microseconds(CAST({} AS BIGINT))",
+            number
+        ),
+        _ => number.to_string(),
+    };
+    let histogram = match (to_number, values.buckets) {
+        ("", _) | (_, 0) => None,
+        (to_number, buckets) => {
+            cancel.check()?;
+            let last = buckets - 1;
+            let histogram_sql = format!(
+                "WITH numbers AS (SELECT {} AS x FROM ({}) WHERE value IS NOT NULL), \
+                 bounds AS (SELECT MIN(x) AS lo, MAX(x) AS hi, (MAX(x) - MIN(x)) / {} AS width FROM numbers), \
+                 counts AS (SELECT CASE WHEN width = 0 THEN 0 ELSE LEAST(CAST(FLOOR((x - lo) / width) AS BIGINT), {}) END AS bucket, COUNT(*) AS count FROM numbers, bounds GROUP BY bucket) \
+                 SELECT {} AS lower, {} AS upper, COALESCE(count, 0) AS count \
+                 FROM bounds, range({}) AS buckets(bucket) LEFT JOIN counts USING (bucket) \
+                 WHERE lo IS NOT NULL AND (bucket = 0 OR hi > lo) ORDER BY bucket;",
+                to_number,
+                rows_sql,
+                buckets,
+                last,
+                from_number("lo + bucket * width"),
+                from_number(&format!(
+                    "CASE WHEN bucket = {} THEN hi ELSE lo + (bucket + 1) * width END",
+                    last
+                )),
+                buckets,
+            );
+            let buckets = conn
+                .prepare(&histogram_sql)?
+                .query(duckdb::params_from_iter(params.iter()))?
+                .map(|row| {
+                    Ok((
+                        row.get::<_, duckdb::types::Value>(0)?,
+                        row.get::<_, duckdb::types::Value>(1)?,
+                        row.get::<_, u64>(2)?,
+                    ))
+                })
+                .collect::<Vec<_>>()?
+                .into_iter()
+                .map(|(lower, upper, count)| {
+                    Ok(summary::HistogramBucket {
+                        lower: json::duckdb_value_to_json_value(lower)?,
+                        upper: json::duckdb_value_to_json_value(upper)?,
+                        count,
+                    })
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            Some(buckets)
+        }
+    };
+
+    Ok(summary::ColumnValues {
+        values: counts,
+        has_more,
+        histogram,
+    })
+}
+
+fn where_sql(conditions: &[String]) -> String {
+    if conditions.is_empty() {
+        "".to_string()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    }
+}
+
+/// Sort keys that make the order of the rows of `query` unique, after
+/// `sort_keys`: its key columns, or all of its columns if it has none.
+fn tiebreaker_keys(
+    key_columns: &[String],
+    columns: &Columns,
+    sort_keys: &[SortKey],
+) -> anyhow::Result<Vec<SortKey>> {
+    let names = columns
+        .iter()
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>();
+    let key_columns = match key_columns.is_empty() {
+        true => &names,
+        false => key_columns,
+    };
+    let mut keys = vec![];
+    for column in key_columns {
+        let sql = column_path_sql(std::slice::from_ref(column), &names)?;
+        if !sort_keys.iter().any(|key| key.sql == sql) {
+            keys.push(SortKey {
+                sql,
+                descending: false,
+                nulls_first: false,
+            });
+        }
+    }
+    Ok(keys)
+}
+
+/// A strong ETag, quoted as in HTTP headers.
+fn etag(version: u64, key: u64) -> String {
+    format!("\"{:016x}{:016x}\"", version, key)
+}
+
+fn hash_sql(sql: &str) -> u64 {
+    let mut hasher = std::hash::DefaultHasher::new();
+    std::hash::Hash::hash(sql, &mut hasher);
+    std::hash::Hasher::finish(&hasher)
+}
+
+fn create_query_view(conn: &Connection, name: &str, sql: &str) -> anyhow::Result<()> {
+    validate_table_name(name)?;
+    let escaped_table_name = escape_table_name(name);
+
+    conn.execute(
+        &format!("CREATE OR REPLACE VIEW {} AS {}", escaped_table_name, sql,),
+        params![],
+    )?;
+    Ok(())
+}
+
+fn spawn_keepalives(tx: broadcast::Sender<DbBroadcastEvent>) {
+    tokio::spawn(async move {
+        loop {
+            let _ = tx.send(DbBroadcastEvent::Ping {
+                data: "keepalive".to_string(),
+            });
+            tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+        }
+    });
+}
+
+fn spawn_file_watcher(rx: mpsc::Receiver<Result<Event>>, db: DB) {
+    tokio::task::spawn_blocking(move || {
+        for res in rx {
+            handle_file_watch_event(res, db.clone());
+        }
+    });
+}
+
+fn handle_file_watch_event(event: Result<Event>, db: DB) {
+    let queries_to_watch = db
+        .config
+        .queries
+        .clone()
+        .iter()
+        .filter(|q| q.source.path().is_some())
+        .cloned()
+        .collect::<Vec<_>>();
+
+    tokio::spawn(async move {
+        match event {
+            Ok(Event {
+                kind: EventKind::Modify(ModifyKind::Data(_)),
+                paths,
+                ..
+            }) => {
+                for path in paths {
+                    for query in queries_to_watch.iter() {
+                        if query.source.path().is_some()
+                            && query.source.path().unwrap().canonicalize()?
+                                == path.canonicalize()?
+                        {
+                            if let Err(e) = db.create_query_views() {
+                                eprintln!("{}", e);
+                            }
+
+                            let mut updated = vec![query.name.clone()];
+                            updated.extend(db.downstream_queries(&query.name)?);
+                            for name in updated {
+                                let _ = db.tx.send(DbBroadcastEvent::QueryUpdated { name });
+                            }
+                        }
+                    }
+                }
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("watch error: {:?}", e),
+        };
+
+        Ok::<(), anyhow::Error>(())
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A database configured by command line `args`.
+    fn test_db(args: &[&str]) -> DB {
+        let args = std::iter::once("frogtable")
+            .chain(args.iter().copied())
+            .map(OsString::from)
+            .collect();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let db = runtime.block_on(DB::new_from_cli_args(args)).unwrap();
+        // Don't wait on the file watcher, which never finishes.
+        runtime.shutdown_background();
+        db
+    }
+
+    /// A token cancelled shortly after, while a query runs.
+    fn cancel_soon() -> std::sync::Arc<CancelToken> {
+        let cancel = std::sync::Arc::new(CancelToken::default());
+        std::thread::spawn({
+            let cancel = cancel.clone();
+            move || {
+                std::thread::sleep(std::time::Duration::from_millis(200));
+                cancel.cancel();
+            }
+        });
+        cancel
+    }
+
+    #[test]
+    fn params_identify_cached_pages_and_counts() {
+        let day = |value: &str| ExecOptions {
+            params: config::Params::from([("day".to_string(), value.to_string())]),
+            ..Default::default()
+        };
+
+        assert_ne!(
+            day("1").cache_key("logs").unwrap(),
+            day("2").cache_key("logs").unwrap()
+        );
+        assert_ne!(
+            day("1").count_token("logs", 0).unwrap(),
+            day("2").count_token("logs", 0).unwrap()
+        );
+        assert_eq!(
+            day("1").cache_key("logs").unwrap(),
+            day("1").cache_key("logs").unwrap()
+        );
+    }
+
+    #[test]
+    fn a_dependency_cycle_does_not_break_other_queries() {
+        let db = test_db(&[
+            "--sql",
+            "SELECT * FROM ping",
+            "--name",
+            "pong",
+            "--",
+            "--sql",
+            "SELECT * FROM pong",
+            "--name",
+            "ping",
+            "--",
+            "--sql",
+            "SELECT 1 AS one",
+            "--name",
+            "solo",
+        ]);
+
+        let error = db.create_query_views().unwrap_err().to_string();
+        assert!(error.contains("`ping`: Queries form a dependency cycle"));
+        assert!(error.contains("`pong`: Queries form a dependency cycle"));
+        assert!(!error.contains("`solo`"));
+        let views = db.views.lock().unwrap();
+        assert!(views.contains_key("solo"));
+        assert!(!views.contains_key("ping"));
+    }
+
+    fn count_options(count: CountMode) -> ExecOptions {
+        ExecOptions {
+            page: 1,
+            per_page: 10,
+            count,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn counts_rows_exactly_or_approximately() {
+        let db = test_db(&["--sql", "SELECT * FROM range(1000)", "--name", "numbers"]);
+
+        let exact = db
+            .exec_query(
+                "numbers",
+                &count_options(CountMode::Exact),
+                &CancelToken::default(),
+            )
+            .unwrap();
+        assert_eq!(exact.total_count, Some(1000));
+        assert!(!exact.total_count_estimated);
+
+        let approximate = db
+            .exec_query(
+                "numbers",
+                &count_options(CountMode::Approximate),
+                &CancelToken::default(),
+            )
+            .unwrap();
+        assert!(approximate.total_count.is_some_and(|count| count > 0));
+        assert!(approximate.total_count_estimated);
+        assert_eq!(approximate.data.len(), 10);
+
+        let conn = db.pool.read();
+        let estimate = estimate_rows(
+            &conn,
+            "SELECT * FROM range(1000) WHERE range > ?",
+            &[duckdb::types::Value::BigInt(10)],
+        )
+        .unwrap();
+        assert!(estimate > 0);
+    }
+
+    #[test]
+    fn deferred_counts_follow_the_page() {
+        let db = test_db(&["--sql", "SELECT * FROM range(1000)", "--name", "numbers"]);
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let mut events = db.tx.subscribe();
+
+        let result = db
+            .exec_query(
+                "numbers",
+                &count_options(CountMode::Deferred),
+                &CancelToken::default(),
+            )
+            .unwrap();
+        assert_eq!(result.total_count, None);
+        assert_eq!(result.data.len(), 10);
+        let token = result.count_token.unwrap();
+
+        let (count_token, total_count) = runtime.block_on(async {
+            loop {
+                if let DbBroadcastEvent::TotalCount {
+                    count_token,
+                    total_count,
+                    ..
+                } = events.recv().await.unwrap()
+                {
+                    return (count_token, total_count);
+                }
+            }
+        });
+        assert_eq!(count_token, token);
+        assert_eq!(total_count, 1000);
+        assert_eq!(db.total_count(&token).unwrap(), Some(1000));
+
+        // Once counted, the count comes with the page.
+        let result = db
+            .exec_query(
+                "numbers",
+                &count_options(CountMode::Deferred),
+                &CancelToken::default(),
+            )
+            .unwrap();
+        assert_eq!(result.total_count, Some(1000));
+        assert_eq!(result.count_token, Some(token));
+    }
+
+    #[test]
+    fn finished_deferred_counts_are_evicted() {
+        let db = test_db(&["--sql", "SELECT * FROM range(1000)", "--name", "numbers"]);
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        {
+            let mut counts = db.counts.lock().unwrap();
+            for i in 0..MAX_DEFERRED_COUNTS - 1 {
+                counts.insert(format!("finished {}", i), Some(1));
+            }
+            counts.insert("running".to_string(), None);
+        }
+
+        let result = db
+            .exec_query(
+                "numbers",
+                &count_options(CountMode::Deferred),
+                &CancelToken::default(),
+            )
+            .unwrap();
+
+        assert!(db.total_count(&result.count_token.unwrap()).is_ok());
+        assert_eq!(db.total_count("running").unwrap(), None);
+        assert_eq!(
+            db.total_count("finished 0").unwrap_err().to_string(),
+            "Unknown count token. Run the query again."
+        );
+        assert_eq!(
+            db.total_count("never issued").unwrap_err().to_string(),
+            "Unknown count token. Run the query again."
+        );
+    }
+
+    #[test]
+    fn untouched_files_are_not_read_again() {
+        const CSV: &str = "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: 8\r\nConnection: close\r\n\r\na,b\n1,2\n";
+        const NOT_MODIFIED: &str = "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n";
+        let (url, server) = crate::http::tests::serve(vec![CSV, NOT_MODIFIED]);
+        let name = format!("test_unchanged_{}", std::process::id());
+        let db = test_db(&["--url", &url, "--format", "csv", "--name", &name]);
+        let config = db.config.sources[0].clone();
+        let path = config.path(&config::Params::new()).unwrap();
+
+        // Only files whose modification time or size changed are read, so
+        // contents changed behind their back go unnoticed.
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+        std::fs::write(&path, "a,b\n3,4\n").unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(modified).unwrap();
+        let refreshed = db
+            .refresh_source(&config, &config::Params::new(), &CancelToken::default())
+            .unwrap();
+
+        assert!(refreshed.is_none());
+        let requests = server.join().unwrap();
+        assert!(requests[1].to_lowercase().contains("if-none-match"));
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(path.with_extension("http.json")).unwrap();
+    }
+
+    #[test]
+    fn aggregates_quoted_columns() {
+        let db = test_db(&[
+            "--sql",
+            r#"SELECT * FROM (VALUES ('Paris', 10), ('Paris', 20), ('Lyon', 5)) AS t("ci""ty", price)"#,
+            "--name",
+            "sales",
+        ]);
+        let sum_by = |column: &str| {
+            let aggregation = serde_json::json!({
+                "group_by": [column],
+                "aggregates": [{"function": {"type": "Sum"}, "column": "price", "name": null}],
+                "pivot": null,
+            });
+            let ordering = serde_json::json!({
+                "column": "ci\"ty",
+                "direction": "Asc",
+                "nulls": null,
+                "case_insensitive": false,
+            });
+            ExecOptions {
+                page: 1,
+                per_page: 10,
+                order_by: vec![serde_json::from_value(ordering).unwrap()],
+                aggregation: Some(serde_json::from_value(aggregation).unwrap()),
+                ..Default::default()
+            }
+        };
+
+        let result = db
+            .exec_query("sales", &sum_by("ci\"ty"), &CancelToken::default())
+            .unwrap();
+
+        assert_eq!(
+            result.data,
+            [
+                // Sums of integers are HUGEINTs, sent as text.
+                vec![serde_json::json!("Lyon"), serde_json::json!("5")],
+                vec![serde_json::json!("Paris"), serde_json::json!("30")],
+            ]
+        );
+        let error = db
+            .exec_query(
+                "sales",
+                &sum_by("city\" FROM sales --"),
+                &CancelToken::default(),
+            )
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "Unknown column `city\" FROM sales --`");
+    }
+
+    #[test]
+    fn summarizing_is_cancellable() {
+        let db = test_db(&[
+            "--sql",
+            "SELECT range AS x FROM range(1000000000)",
+            "--name",
+            "big",
+        ]);
+        let cancel = cancel_soon();
+
+        let result = db.summarize_query("big", &ExecOptions::default(), 0, &cancel);
+
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            "Query `big` was cancelled"
+        );
+    }
+
+    #[test]
+    fn listing_column_values_is_cancellable() {
+        let db = test_db(&[
+            "--sql",
+            "SELECT range AS x FROM range(1000000000)",
+            "--name",
+            "big",
+        ]);
+        let cancel = cancel_soon();
+        let values = summary::ValuesOptions {
+            column: "x".to_string(),
+            exclude_column_filter: false,
+            prefix: None,
+            limit: 10,
+            buckets: 10,
+        };
+
+        let result = db.column_values("big", &ExecOptions::default(), &values, &cancel);
+
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            "Query `big` was cancelled"
+        );
+    }
 }
@@ -0,0 +1,199 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{ObjectName, Query, Statement, Visit, Visitor};
+use sqlparser::dialect::DuckDbDialect;
+use sqlparser::parser::Parser;
+
+/// DuckDB identifiers are case-insensitive, even when quoted, so names are
+/// compared in lowercase.
+pub fn normalize_name(name: &str) -> String {
+    name.to_lowercase()
+}
+
+/// The (normalized) names of the tables and views `sql` reads from.
+///
+/// Only the last part of a qualified name is kept, and references to CTEs
+/// defined in the SQL itself are left out. If `sqlparser` cannot parse the
+/// SQL, every identifier-like word in it is returned instead, which errs on
+/// the side of refreshing too much.
+pub fn referenced_tables(sql: &str) -> BTreeSet<String> {
+    match Parser::parse_sql(&DuckDbDialect {}, sql) {
+        Ok(statements) => {
+            let mut visitor = TableReferences::default();
+            let _ = statements.visit(&mut visitor);
+            visitor.tables
+        }
+        Err(e) => {
+            eprintln!("Could not parse SQL to find its dependencies: {}", e);
+            sql.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+                .filter(|word| !word.is_empty())
+                .map(normalize_name)
+                .collect()
+        }
+    }
+}
+
+/// The (normalized) names of the tables and views created by `sql`, such as
+/// an initializer's `CREATE TABLE` statements. Returns nothing when the SQL
+/// cannot be parsed.
+pub fn created_tables(sql: &str) -> BTreeSet<String> {
+    let Ok(statements) = Parser::parse_sql(&DuckDbDialect {}, sql) else {
+        return BTreeSet::new();
+    };
+    statements
+        .iter()
+        .filter_map(|statement| match statement {
+            Statement::CreateTable(create) => Some(&create.name),
+            Statement::CreateView { name, .. } => Some(name),
+            _ => None,
+        })
+        .filter_map(|name| name.0.last().and_then(|part| part.as_ident()))
+        .map(|ident| normalize_name(&ident.value))
+        .collect()
+}
+
+/// The CTEs of one `WITH` clause, and how many of them are visible at the
+/// current point of the traversal.
+struct CteScope {
+    names: Vec<String>,
+    queries: Vec<*const Query>,
+    recursive: bool,
+    visible: usize,
+}
+
+#[derive(Default)]
+struct TableReferences {
+    scopes: Vec<CteScope>,
+    tables: BTreeSet<String>,
+}
+
+impl TableReferences {
+    fn is_cte(&self, name: &str) -> bool {
+        self.scopes.iter().any(|scope| {
+            let visible = if scope.recursive {
+                scope.names.len()
+            } else {
+                scope.visible
+            };
+            scope.names[..visible].iter().any(|cte| cte == name)
+        })
+    }
+
+    /// The scope and index of the CTE whose body is `query`, if any.
+    fn cte_of(&self, query: &Query) -> Option<(usize, usize)> {
+        let scope_idx = self.scopes.len().checked_sub(1)?;
+        let cte_idx = self.scopes[scope_idx]
+            .queries
+            .iter()
+            .position(|q| std::ptr::eq(*q, query))?;
+        Some((scope_idx, cte_idx))
+    }
+}
+
+impl Visitor for TableReferences {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<Self::Break> {
+        // A non-recursive CTE can only see the CTEs defined before it.
+        if let Some((scope_idx, cte_idx)) = self.cte_of(query) {
+            self.scopes[scope_idx].visible = cte_idx;
+        }
+
+        let (names, queries, recursive) = match &query.with {
+            Some(with) => (
+                with.cte_tables
+                    .iter()
+                    .map(|cte| normalize_name(&cte.alias.name.value))
+                    .collect(),
+                with.cte_tables
+                    .iter()
+                    .map(|cte| &*cte.query as *const Query)
+                    .collect(),
+                with.recursive,
+            ),
+            None => (vec![], vec![], false),
+        };
+        self.scopes.push(CteScope {
+            names,
+            queries,
+            recursive,
+            visible: 0,
+        });
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_query(&mut self, query: &Query) -> ControlFlow<Self::Break> {
+        self.scopes.pop();
+        // Everything after a CTE (later CTEs and the main query) can see it.
+        if let Some((scope_idx, cte_idx)) = self.cte_of(query) {
+            self.scopes[scope_idx].visible = cte_idx + 1;
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_relation(&mut self, relation: &ObjectName) -> ControlFlow<Self::Break> {
+        let Some(ident) = relation.0.last().and_then(|part| part.as_ident()) else {
+            return ControlFlow::Continue(());
+        };
+        let name = normalize_name(&ident.value);
+        if relation.0.len() > 1 || !self.is_cte(&name) {
+            self.tables.insert(name);
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Order `roots` and the nodes of `graph` they depend on (each node mapped to
+/// the nodes it depends on) so that every node comes after its dependencies.
+/// Fails with the offending path if one of those nodes is part of a cycle;
+/// cycles elsewhere in the graph are ignored.
+pub fn dependency_order<'a>(
+    graph: &BTreeMap<String, Vec<String>>,
+    roots: impl IntoIterator<Item = &'a str>,
+) -> anyhow::Result<Vec<String>> {
+    #[derive(PartialEq)]
+    enum State {
+        Visiting,
+        Done,
+    }
+
+    fn visit(
+        node: &str,
+        graph: &BTreeMap<String, Vec<String>>,
+        states: &mut BTreeMap<String, State>,
+        path: &mut Vec<String>,
+        order: &mut Vec<String>,
+    ) -> anyhow::Result<()> {
+        match states.get(node) {
+            Some(State::Done) => return Ok(()),
+            Some(State::Visiting) => {
+                let start = path.iter().position(|n| n == node).unwrap_or(0);
+                let mut cycle = path[start..].to_vec();
+                cycle.push(node.to_string());
+                return Err(anyhow::anyhow!(
+                    "Queries form a dependency cycle: {}",
+                    cycle.join(" -> ")
+                ));
+            }
+            None => {}
+        }
+
+        states.insert(node.to_string(), State::Visiting);
+        path.push(node.to_string());
+        for dependency in graph.get(node).into_iter().flatten() {
+            visit(dependency, graph, states, path, order)?;
+        }
+        path.pop();
+        states.insert(node.to_string(), State::Done);
+        order.push(node.to_string());
+        Ok(())
+    }
+
+    let mut states = BTreeMap::new();
+    let mut order = vec![];
+    for node in roots {
+        visit(node, graph, &mut states, &mut vec![], &mut order)?;
+    }
+    Ok(order)
+}
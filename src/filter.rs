@@ -0,0 +1,591 @@
+use std::ops::ControlFlow;
+
+use duckdb::types::Value;
+use serde::{Deserialize, Serialize};
+use sqlparser::ast::{
+    visit_expressions, BinaryOperator, Expr, Query, UnaryOperator, Visit, Visitor,
+};
+use sqlparser::dialect::DuckDbDialect;
+use sqlparser::parser::Parser;
+use sqlparser::tokenizer::Token;
+use ts_rs::TS;
+
+use crate::db::column_path_sql;
+use crate::deps::normalize_name;
+
+/// A filter on the rows of a query result, compiled to a parameterized
+/// `WHERE` clause.
+#[derive(TS, Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type")]
+pub enum Filter {
+    /// Matches when every filter matches. An empty list matches everything.
+    And {
+        filters: Vec<Filter>,
+    },
+    /// Matches when any filter matches. An empty list matches nothing.
+    Or {
+        filters: Vec<Filter>,
+    },
+    Not {
+        filter: Box<Filter>,
+    },
+    Condition(Condition),
+}
+
+#[derive(TS, Serialize, Deserialize, Debug, Clone)]
+pub struct Condition {
+    /// The column, followed by the fields to descend into for struct
+    /// columns, e.g. `["address", "city"]`.
+    pub path: Vec<String>,
+    pub op: FilterOp,
+    /// The operand. Required by every operator except `IsNull` and
+    /// `IsNotNull`; `In` and `NotIn` take a `List`.
+    pub value: Option<FilterValue>,
+}
+
+#[derive(TS, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum FilterOp {
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    Like,
+    NotLike,
+    ILike,
+    In,
+    NotIn,
+    IsNull,
+    IsNotNull,
+}
+
+#[derive(TS, Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", content = "value")]
+pub enum FilterValue {
+    Null,
+    Boolean(bool),
+    Integer(#[ts(type = "number")] i64),
+    Float(f64),
+    Text(String),
+    List(Vec<FilterValue>),
+}
+
+impl FilterValue {
+    fn to_param(&self) -> anyhow::Result<Value> {
+        Ok(match self {
+            FilterValue::Null => Value::Null,
+            FilterValue::Boolean(b) => Value::Boolean(*b),
+            FilterValue::Integer(i) => Value::BigInt(*i),
+            FilterValue::Float(f) => Value::Double(*f),
+            FilterValue::Text(s) => Value::Text(s.clone()),
+            FilterValue::List(_) => {
+                return Err(anyhow::anyhow!(
+                    "Invalid filter: a list can only be used with `In` and `NotIn`"
+                ))
+            }
+        })
+    }
+}
+
+impl Filter {
+    /// Compile the filter to SQL, with its values as `?` placeholders that are
+    /// appended to `params`. Columns must be among `columns`.
+    pub fn to_sql(&self, columns: &[String], params: &mut Vec<Value>) -> anyhow::Result<String> {
+        match self {
+            Filter::And { filters } if filters.is_empty() => Ok("TRUE".to_string()),
+            Filter::Or { filters } if filters.is_empty() => Ok("FALSE".to_string()),
+            Filter::And { filters } | Filter::Or { filters } => {
+                let separator = match self {
+                    Filter::And { .. } => " AND ",
+                    _ => " OR ",
+                };
+                let parts = filters
+                    .iter()
+                    .map(|filter| filter.to_sql(columns, params))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                Ok(format!("({})", parts.join(separator)))
+            }
+            Filter::Not { filter } => Ok(format!("(NOT {})", filter.to_sql(columns, params)?)),
+            Filter::Condition(condition) => condition.to_sql(columns, params),
+        }
+    }
+
+    /// The filter without its conditions on `path` or the fields under it,
+    /// or `None` if nothing is left. Only conditions that can be dropped
+    /// without changing the meaning of the others are: those combined with
+    /// `And`, or whole `Or` and `Not` filters that only apply to `path`.
+    pub fn without_path(&self, path: &[String]) -> Option<Filter> {
+        match self {
+            Filter::And { filters } => {
+                let filters = filters
+                    .iter()
+                    .filter_map(|filter| filter.without_path(path))
+                    .collect::<Vec<_>>();
+                (!filters.is_empty()).then_some(Filter::And { filters })
+            }
+            _ if self.only_on(path) => None,
+            _ => Some(self.clone()),
+        }
+    }
+
+    fn only_on(&self, path: &[String]) -> bool {
+        match self {
+            Filter::And { filters } | Filter::Or { filters } => {
+                !filters.is_empty() && filters.iter().all(|filter| filter.only_on(path))
+            }
+            Filter::Not { filter } => filter.only_on(path),
+            Filter::Condition(condition) => {
+                condition.path.len() >= path.len()
+                    && condition
+                        .path
+                        .iter()
+                        .zip(path)
+                        .enumerate()
+                        .all(|(i, (a, b))| match i {
+                            0 => normalize_name(a) == normalize_name(b),
+                            _ => a == b,
+                        })
+            }
+        }
+    }
+}
+
+impl Condition {
+    fn to_sql(&self, columns: &[String], params: &mut Vec<Value>) -> anyhow::Result<String> {
+        let column = column_path_sql(&self.path, columns)
+            .map_err(|e| anyhow::anyhow!("Invalid filter: {}", e))?;
+        let value = || {
+            self.value.as_ref().ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Invalid filter: `{:?}` on `{}` needs a value",
+                    self.op,
+                    column
+                )
+            })
+        };
+
+        let comparison = |operator: &str, params: &mut Vec<Value>| -> anyhow::Result<String> {
+            params.push(value()?.to_param()?);
+            Ok(format!("{} {} ?", column, operator))
+        };
+
+        match self.op {
+            FilterOp::Eq => comparison("=", params),
+            FilterOp::NotEq => comparison("<>", params),
+            FilterOp::Lt => comparison("<", params),
+            FilterOp::LtEq => comparison("<=", params),
+            FilterOp::Gt => comparison(">", params),
+            FilterOp::GtEq => comparison(">=", params),
+            FilterOp::Like => comparison("LIKE", params),
+            FilterOp::NotLike => comparison("NOT LIKE", params),
+            FilterOp::ILike => comparison("ILIKE", params),
+            FilterOp::In | FilterOp::NotIn => {
+                let FilterValue::List(values) = value()? else {
+                    return Err(anyhow::anyhow!(
+                        "Invalid filter: `{:?}` on `{}` needs a list",
+                        self.op,
+                        column
+                    ));
+                };
+                if values.is_empty() {
+                    return Ok(if self.op == FilterOp::In {
+                        "FALSE"
+                    } else {
+                        "TRUE"
+                    }
+                    .to_string());
+                }
+                for value in values {
+                    params.push(value.to_param()?);
+                }
+                let placeholders = vec!["?"; values.len()].join(", ");
+                let operator = if self.op == FilterOp::In {
+                    "IN"
+                } else {
+                    "NOT IN"
+                };
+                Ok(format!("{} {} ({})", column, operator, placeholders))
+            }
+            FilterOp::IsNull => Ok(format!("{} IS NULL", column)),
+            FilterOp::IsNotNull => Ok(format!("{} IS NOT NULL", column)),
+        }
+    }
+}
+
+/// Whether `expr` evaluates to a boolean, as far as its shape tells.
+/// Column references and function calls are given the benefit of the doubt;
+/// DuckDB rejects them later if they are not boolean.
+fn is_boolean(expr: &Expr) -> bool {
+    match expr {
+        Expr::Nested(expr) => is_boolean(expr),
+        Expr::BinaryOp { op, .. } => matches!(
+            op,
+            BinaryOperator::Eq
+                | BinaryOperator::NotEq
+                | BinaryOperator::Lt
+                | BinaryOperator::LtEq
+                | BinaryOperator::Gt
+                | BinaryOperator::GtEq
+                | BinaryOperator::Spaceship
+                | BinaryOperator::And
+                | BinaryOperator::Or
+                | BinaryOperator::Xor
+        ),
+        Expr::UnaryOp {
+            op: UnaryOperator::Not,
+            ..
+        } => true,
+        Expr::IsNull(_)
+        | Expr::IsNotNull(_)
+        | Expr::IsTrue(_)
+        | Expr::IsNotTrue(_)
+        | Expr::IsFalse(_)
+        | Expr::IsNotFalse(_)
+        | Expr::IsUnknown(_)
+        | Expr::IsNotUnknown(_)
+        | Expr::IsDistinctFrom(..)
+        | Expr::IsNotDistinctFrom(..)
+        | Expr::InList { .. }
+        | Expr::Between { .. }
+        | Expr::Like { .. }
+        | Expr::ILike { .. }
+        | Expr::SimilarTo { .. }
+        | Expr::RLike { .. }
+        | Expr::Identifier(_)
+        | Expr::CompoundIdentifier(_)
+        | Expr::Function(_) => true,
+        Expr::Value(value) => matches!(value.value, sqlparser::ast::Value::Boolean(_)),
+        _ => false,
+    }
+}
+
+/// Finds queries nested anywhere in an expression, e.g. `ARRAY(SELECT ...)`
+/// or a subquery passed to a function, not only `(SELECT ...)`.
+struct FindQuery;
+
+impl Visitor for FindQuery {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, _query: &Query) -> ControlFlow<()> {
+        ControlFlow::Break(())
+    }
+}
+
+/// Parse a single SQL expression without subqueries.
+fn parse_expression(sql: &str) -> anyhow::Result<Expr> {
+    let dialect = DuckDbDialect {};
+    let mut parser = Parser::new(&dialect).try_with_sql(sql)?;
+    let expr = parser.parse_expr()?;
+    if parser.peek_token().token != Token::EOF {
+        return Err(anyhow::anyhow!(
+            "expected a single expression, found `{}`",
+            parser.peek_token().token
+        ));
+    }
+
+    if expr.visit(&mut FindQuery).is_break() {
+        return Err(anyhow::anyhow!("subqueries are not allowed"));
+    }
+
+    Ok(expr)
+}
+
+/// Check that a raw SQL filter is a single boolean expression without
+/// subqueries, and return it re-rendered from its syntax tree, so that
+/// nothing but the parsed expression reaches DuckDB.
+pub fn validate_raw_filter(sql: &str) -> anyhow::Result<String> {
+    let expr = parse_expression(sql).map_err(|e| anyhow::anyhow!("Invalid filter: {}", e))?;
+
+    if !is_boolean(&expr) {
+        return Err(anyhow::anyhow!(
+            "Invalid filter: `{}` is not a boolean expression",
+            expr
+        ));
+    }
+
+    Ok(expr.to_string())
+}
+
+/// Aggregate functions, which cannot compute a column row by row.
+const AGGREGATE_FUNCTIONS: &[&str] = &[
+    "any_value",
+    "approx_count_distinct",
+    "approx_quantile",
+    "arg_max",
+    "arg_min",
+    "array_agg",
+    "avg",
+    "bit_and",
+    "bit_or",
+    "bit_xor",
+    "bool_and",
+    "bool_or",
+    "corr",
+    "count",
+    "count_star",
+    "covar_pop",
+    "covar_samp",
+    "entropy",
+    "favg",
+    "first",
+    "fsum",
+    "group_concat",
+    "histogram",
+    "kurtosis",
+    "last",
+    "list",
+    "listagg",
+    "mad",
+    "max",
+    "max_by",
+    "mean",
+    "median",
+    "min",
+    "min_by",
+    "mode",
+    "product",
+    "quantile",
+    "quantile_cont",
+    "quantile_disc",
+    "regr_avgx",
+    "regr_avgy",
+    "regr_count",
+    "regr_intercept",
+    "regr_r2",
+    "regr_slope",
+    "regr_sxx",
+    "regr_sxy",
+    "regr_syy",
+    "skewness",
+    "stddev",
+    "stddev_pop",
+    "stddev_samp",
+    "string_agg",
+    "sum",
+    "var_pop",
+    "var_samp",
+    "variance",
+];
+
+/// Check that the expression of a computed column is a single scalar
+/// expression: no subqueries, aggregates or window functions. Returns it
+/// re-rendered from its syntax tree, like `validate_raw_filter`.
+pub fn validate_scalar_expression(sql: &str) -> anyhow::Result<String> {
+    let invalid = |e: anyhow::Error| anyhow::anyhow!("Invalid expression `{}`: {}", sql, e);
+    let expr = parse_expression(sql).map_err(invalid)?;
+
+    let not_scalar = visit_expressions(&expr, |expr| match expr {
+        Expr::Function(function) => {
+            let name = function.name.to_string().to_lowercase();
+            if function.over.is_some() {
+                ControlFlow::Break(format!("`{}` is a window function", name))
+            } else if function.filter.is_some()
+                || !function.within_group.is_empty()
+                || AGGREGATE_FUNCTIONS.contains(&name.as_str())
+            {
+                ControlFlow::Break(format!("`{}` is an aggregate function", name))
+            } else {
+                ControlFlow::Continue(())
+            }
+        }
+        _ => ControlFlow::Continue(()),
+    });
+    if let ControlFlow::Break(reason) = not_scalar {
+        return Err(invalid(anyhow::anyhow!(reason)));
+    }
+
+    Ok(expr.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn columns() -> Vec<String> {
+        ["age", "name", "City", "address", "we\"ird"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect()
+    }
+
+    fn filter(json: &str) -> Filter {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn compile(filter: &Filter) -> anyhow::Result<(String, Vec<Value>)> {
+        let mut params = vec![];
+        let sql = filter.to_sql(&columns(), &mut params)?;
+        Ok((sql, params))
+    }
+
+    #[test]
+    fn compiles_to_placeholders() {
+        let filter = filter(
+            r#"{"type": "And", "filters": [
+                {"type": "Condition", "path": ["age"], "op": "GtEq", "value": {"type": "Integer", "value": 18}},
+                {"type": "Or", "filters": [
+                    {"type": "Condition", "path": ["name"], "op": "Like", "value": {"type": "Text", "value": "A%"}},
+                    {"type": "Condition", "path": ["city"], "op": "NotIn", "value": {"type": "List", "value": [
+                        {"type": "Text", "value": "Paris"}, {"type": "Null"}
+                    ]}}
+                ]},
+                {"type": "Not", "filter": {"type": "Condition", "path": ["name"], "op": "IsNull", "value": null}}
+            ]}"#,
+        );
+
+        let (sql, params) = compile(&filter).unwrap();
+
+        assert_eq!(
+            sql,
+            r#"("age" >= ? AND ("name" LIKE ? OR "City" NOT IN (?, ?)) AND (NOT "name" IS NULL))"#
+        );
+        assert_eq!(
+            params,
+            [
+                Value::BigInt(18),
+                Value::Text("A%".to_string()),
+                Value::Text("Paris".to_string()),
+                Value::Null,
+            ]
+        );
+    }
+
+    #[test]
+    fn binds_values_rather_than_inlining_them() {
+        let filter = filter(
+            r#"{"type": "Condition", "path": ["name"], "op": "Eq", "value": {"type": "Text", "value": "x' OR '1'='1"}}"#,
+        );
+
+        let (sql, params) = compile(&filter).unwrap();
+
+        assert_eq!(sql, r#""name" = ?"#);
+        assert_eq!(params, [Value::Text("x' OR '1'='1".to_string())]);
+    }
+
+    #[test]
+    fn quotes_identifiers() {
+        let is_null = |path: &str| {
+            filter(&format!(
+                r#"{{"type": "Condition", "path": {}, "op": "IsNotNull", "value": null}}"#,
+                path
+            ))
+        };
+
+        assert_eq!(
+            compile(&is_null(r#"["we\"ird"]"#)).unwrap().0,
+            r#""we""ird" IS NOT NULL"#
+        );
+        assert_eq!(
+            compile(&is_null(r#"["address", "it's"]"#)).unwrap().0,
+            r#""address"['it''s'] IS NOT NULL"#
+        );
+    }
+
+    #[test]
+    fn empty_filters() {
+        let (sql, params) = compile(&filter(r#"{"type": "And", "filters": []}"#)).unwrap();
+        assert_eq!(sql, "TRUE");
+        assert!(params.is_empty());
+        let (sql, _) = compile(&filter(r#"{"type": "Or", "filters": []}"#)).unwrap();
+        assert_eq!(sql, "FALSE");
+        let (sql, _) = compile(&filter(
+            r#"{"type": "Condition", "path": ["age"], "op": "In", "value": {"type": "List", "value": []}}"#,
+        ))
+        .unwrap();
+        assert_eq!(sql, "FALSE");
+    }
+
+    #[test]
+    fn rejects_unknown_columns() {
+        let filter = filter(
+            r#"{"type": "Condition", "path": ["age\" OR 1=1 --"], "op": "Eq", "value": {"type": "Integer", "value": 1}}"#,
+        );
+
+        let error = compile(&filter).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Invalid filter: Unknown column `age\" OR 1=1 --`"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_operators() {
+        let unknown_op = serde_json::from_str::<Filter>(
+            r#"{"type": "Condition", "path": ["age"], "op": "Regexp", "value": {"type": "Text", "value": "."}}"#,
+        );
+        let unknown_combinator =
+            serde_json::from_str::<Filter>(r#"{"type": "Xor", "filters": []}"#);
+
+        assert!(unknown_op.is_err());
+        assert!(unknown_combinator.is_err());
+    }
+
+    #[test]
+    fn rejects_missing_or_misplaced_values() {
+        let condition = |op: &str, value: &str| {
+            filter(&format!(
+                r#"{{"type": "Condition", "path": ["age"], "op": "{}", "value": {}}}"#,
+                op, value
+            ))
+        };
+
+        assert!(compile(&condition("Eq", "null")).is_err());
+        assert!(compile(&condition("In", r#"{"type": "Integer", "value": 1}"#)).is_err());
+        assert!(compile(&condition("Eq", r#"{"type": "List", "value": []}"#)).is_err());
+    }
+
+    #[test]
+    fn validates_raw_filters() {
+        assert_eq!(
+            validate_raw_filter("age > 1 and name like 'A%'").unwrap(),
+            "age > 1 AND name LIKE 'A%'"
+        );
+        assert!(validate_raw_filter("age > 1; DROP TABLE users").is_err());
+        assert!(validate_raw_filter("age + 1").is_err());
+        assert!(validate_raw_filter("age IN (SELECT 1)").is_err());
+        assert!(validate_raw_filter(
+            "len(ARRAY(SELECT content FROM read_text('/tmp/secret.txt'))) > 0"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn validates_scalar_expressions() {
+        assert_eq!(
+            validate_scalar_expression("price*qty").unwrap(),
+            "price * qty"
+        );
+        assert!(validate_scalar_expression("sum(price)").is_err());
+        assert!(validate_scalar_expression("row_number() OVER ()").is_err());
+        assert!(validate_scalar_expression("(SELECT 1)").is_err());
+        for sql in [
+            "ARRAY(SELECT 1)",
+            "list_transform((SELECT [content] FROM read_text('/etc/passwd')), x -> x)",
+        ] {
+            assert_eq!(
+                validate_scalar_expression(sql).unwrap_err().to_string(),
+                format!("Invalid expression `{}`: subqueries are not allowed", sql)
+            );
+        }
+    }
+
+    #[test]
+    fn drops_conditions_on_a_path() {
+        let filter = filter(
+            r#"{"type": "And", "filters": [
+                {"type": "Condition", "path": ["address", "city"], "op": "IsNull", "value": null},
+                {"type": "Or", "filters": [
+                    {"type": "Condition", "path": ["address"], "op": "IsNull", "value": null},
+                    {"type": "Condition", "path": ["age"], "op": "IsNull", "value": null}
+                ]}
+            ]}"#,
+        );
+
+        let (sql, _) = compile(&filter.without_path(&["Address".to_string()]).unwrap()).unwrap();
+
+        assert_eq!(sql, r#"(("address" IS NULL OR "age" IS NULL))"#);
+        assert!(filter.without_path(&[]).is_none());
+    }
+}
@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+pub fn source_id(name: &str) -> String {
+    format!("source:{}", name)
+}
+
+pub fn initializer_id(idx: usize) -> String {
+    format!("initializer:{}", idx)
+}
+
+pub fn query_id(name: &str) -> String {
+    format!("query:{}", name)
+}
+
+#[derive(TS, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum NodeKind {
+    Source,
+    Initializer,
+    Query,
+}
+
+#[derive(TS, Serialize, Deserialize, Debug, Clone)]
+pub struct GraphNode {
+    /// Unique id, such as `source:users` or `query:active_users`.
+    pub id: String,
+    pub kind: NodeKind,
+    pub name: String,
+    /// RFC 3339 timestamp of the last refresh of a source, or the last run of
+    /// an initializer or query.
+    pub last_run_at: Option<String>,
+    pub last_run_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// `to` reads from what `from` provides.
+#[derive(TS, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GraphEdge {
+    pub from: String,
+    pub to: String,
+}
+
+/// Sources, initializers and queries, and how data flows between them.
+#[derive(TS, Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Graph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+fn escape_dot(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+impl Graph {
+    pub fn add_edge(&mut self, from: String, to: String) {
+        let edge = GraphEdge { from, to };
+        if !self.edges.contains(&edge) {
+            self.edges.push(edge);
+        }
+    }
+
+    /// Render the graph in Graphviz DOT format. Nodes that failed on their
+    /// last run are drawn in red.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph frogtable {\n    rankdir=LR;\n");
+        for node in self.nodes.iter() {
+            let shape = match node.kind {
+                NodeKind::Source => "cylinder",
+                NodeKind::Initializer => "note",
+                NodeKind::Query => "box",
+            };
+            let status = match (&node.error, node.last_run_ms) {
+                (Some(_), _) => "failed".to_string(),
+                (None, Some(ms)) => format!("{} ms", ms),
+                (None, None) => "not run".to_string(),
+            };
+            let label = format!("{}\n{}", node.name, status);
+            let color = if node.error.is_some() { "red" } else { "black" };
+            dot.push_str(&format!(
+                "    \"{}\" [label=\"{}\", shape={}, color={}];\n",
+                escape_dot(&node.id),
+                escape_dot(&label),
+                shape,
+                color
+            ));
+        }
+        for edge in self.edges.iter() {
+            dot.push_str(&format!(
+                "    \"{}\" -> \"{}\";\n",
+                escape_dot(&edge.from),
+                escape_dot(&edge.to)
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
@@ -0,0 +1,265 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::UrlSource;
+
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+/// Validators from the last successful response, stored next to the
+/// downloaded file so that unchanged resources are not downloaded again.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheMetadata {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+fn cache_metadata_path(out_path: &Path) -> PathBuf {
+    out_path.with_extension("http.json")
+}
+
+fn read_cache_metadata(source: &UrlSource, out_path: &Path) -> Option<CacheMetadata> {
+    if !out_path.exists() {
+        return None;
+    }
+    let contents = std::fs::read_to_string(cache_metadata_path(out_path)).ok()?;
+    let metadata: CacheMetadata = serde_json::from_str(&contents).ok()?;
+    // A cached response for a different URL is useless.
+    if metadata.url != source.url {
+        return None;
+    }
+    Some(metadata)
+}
+
+/// Download `source` into `out_path`, sending `If-None-Match` /
+/// `If-Modified-Since` when a previous download is available. A `304 Not
+/// Modified` response leaves the existing file in place.
+pub fn fetch(name: &str, source: &UrlSource, out_path: &Path) -> anyhow::Result<()> {
+    let timeout = Duration::from_secs(source.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS));
+    let mut request = ureq::get(&source.url).timeout(timeout);
+
+    for (header, value) in source.headers.iter() {
+        request = request.set(header, value);
+    }
+
+    if let Some(env_var) = &source.bearer_token_env {
+        let token = std::env::var(env_var).map_err(|_| {
+            anyhow::anyhow!(
+                "Error refreshing source `{}`: environment variable `{}` is not set",
+                name,
+                env_var
+            )
+        })?;
+        request = request.set("Authorization", &format!("Bearer {}", token));
+    }
+
+    let cached = read_cache_metadata(source, out_path);
+    if let Some(metadata) = &cached {
+        if let Some(etag) = &metadata.etag {
+            request = request.set("If-None-Match", etag);
+        }
+        if let Some(last_modified) = &metadata.last_modified {
+            request = request.set("If-Modified-Since", last_modified);
+        }
+    }
+
+    let response = match request.call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(status, response)) => {
+            let body = response.into_string().unwrap_or_default();
+            return Err(anyhow::anyhow!(
+                "Error refreshing source `{}` (HTTP {}).\n\nGET {}\n{}",
+                name,
+                status,
+                source.url,
+                body
+            ));
+        }
+        Err(e) => {
+            return Err(anyhow::anyhow!(
+                "Error refreshing source `{}`.\n\nGET {}\n{}",
+                name,
+                source.url,
+                e
+            ));
+        }
+    };
+
+    if response.status() == 304 {
+        return Ok(());
+    }
+
+    if !(200..300).contains(&response.status()) {
+        return Err(anyhow::anyhow!(
+            "Error refreshing source `{}` (HTTP {} {}).\n\nGET {}",
+            name,
+            response.status(),
+            response.status_text(),
+            source.url
+        ));
+    }
+
+    let metadata = CacheMetadata {
+        url: source.url.clone(),
+        etag: response.header("ETag").map(|s| s.to_string()),
+        last_modified: response.header("Last-Modified").map(|s| s.to_string()),
+    };
+
+    // Write to a temporary file first so that a failed download does not
+    // clobber the previous copy.
+    let tmp_path = out_path.with_extension("download");
+    let mut file = std::fs::File::create(&tmp_path)?;
+    std::io::copy(&mut response.into_reader(), &mut file)?;
+    std::fs::rename(&tmp_path, out_path)?;
+
+    std::fs::write(
+        cache_metadata_path(out_path),
+        serde_json::to_string(&metadata)?,
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    use super::*;
+
+    /// Serve `responses` in order, one per connection, and return the head of
+    /// each request received.
+    pub(crate) fn serve(responses: Vec<&'static str>) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/data.json", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let mut requests = vec![];
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" || line.is_empty() {
+                        break;
+                    }
+                    request.push_str(&line);
+                }
+                stream.write_all(response.as_bytes()).unwrap();
+                requests.push(request);
+            }
+            requests
+        });
+        (url, handle)
+    }
+
+    fn source(url: &str) -> UrlSource {
+        UrlSource {
+            url: url.to_string(),
+            format: crate::config::DataFormat::Json,
+            headers: vec![],
+            bearer_token_env: None,
+            timeout_secs: Some(5),
+        }
+    }
+
+    fn out_path(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join("frogtable-http-tests")
+            .join(format!("{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("data.json")
+    }
+
+    const OK: &str = "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nLast-Modified: Sat, 17 Oct 2026 10:00:00 GMT\r\nContent-Length: 9\r\nConnection: close\r\n\r\n[{\"a\":1}]";
+    const NOT_MODIFIED: &str = "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n";
+    const ERROR: &str =
+        "HTTP/1.1 500 Internal Server Error\r\nContent-Length: 4\r\nConnection: close\r\n\r\nboom";
+
+    #[test]
+    fn downloads_and_stores_validators() {
+        let (url, server) = serve(vec![OK]);
+        let out_path = out_path("download");
+
+        fetch("test", &source(&url), &out_path).unwrap();
+
+        assert_eq!(std::fs::read_to_string(&out_path).unwrap(), "[{\"a\":1}]");
+        let metadata = read_cache_metadata(&source(&url), &out_path).unwrap();
+        assert_eq!(metadata.etag.as_deref(), Some("\"v1\""));
+        assert_eq!(
+            metadata.last_modified.as_deref(),
+            Some("Sat, 17 Oct 2026 10:00:00 GMT")
+        );
+        let requests = server.join().unwrap();
+        assert!(!requests[0].to_lowercase().contains("if-none-match"));
+    }
+
+    #[test]
+    fn not_modified_keeps_the_previous_download() {
+        let (url, server) = serve(vec![OK, NOT_MODIFIED]);
+        let out_path = out_path("not-modified");
+
+        fetch("test", &source(&url), &out_path).unwrap();
+        fetch("test", &source(&url), &out_path).unwrap();
+
+        assert_eq!(std::fs::read_to_string(&out_path).unwrap(), "[{\"a\":1}]");
+        let requests = server.join().unwrap();
+        let second = requests[1].to_lowercase();
+        assert!(second.contains("if-none-match: \"v1\""));
+        assert!(second.contains("if-modified-since: sat, 17 oct 2026 10:00:00 gmt"));
+    }
+
+    #[test]
+    fn error_status_fails_and_keeps_the_previous_download() {
+        let (url, server) = serve(vec![OK, ERROR]);
+        let out_path = out_path("error");
+
+        fetch("test", &source(&url), &out_path).unwrap();
+        let error = fetch("test", &source(&url), &out_path).unwrap_err();
+
+        let message = error.to_string();
+        assert!(message.contains("HTTP 500"), "{}", message);
+        assert!(message.contains("boom"), "{}", message);
+        assert_eq!(std::fs::read_to_string(&out_path).unwrap(), "[{\"a\":1}]");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn sends_custom_headers_and_bearer_token() {
+        let (url, server) = serve(vec![OK]);
+        let out_path = out_path("headers");
+        std::env::set_var("FROGTABLE_HTTP_TEST_TOKEN", "s3cret");
+        let source = UrlSource {
+            headers: vec![("X-Api-Key".to_string(), "key".to_string())],
+            bearer_token_env: Some("FROGTABLE_HTTP_TEST_TOKEN".to_string()),
+            ..source(&url)
+        };
+
+        fetch("test", &source, &out_path).unwrap();
+
+        let request = server.join().unwrap().remove(0).to_lowercase();
+        assert!(request.contains("x-api-key: key"), "{}", request);
+        assert!(
+            request.contains("authorization: bearer s3cret"),
+            "{}",
+            request
+        );
+    }
+
+    #[test]
+    fn missing_bearer_token_fails_before_the_request() {
+        let source = UrlSource {
+            bearer_token_env: Some("FROGTABLE_HTTP_TEST_UNSET".to_string()),
+            ..source("http://127.0.0.1:9/data.json")
+        };
+
+        let error = fetch("test", &source, &out_path("unset-token")).unwrap_err();
+
+        assert!(error.to_string().contains("FROGTABLE_HTTP_TEST_UNSET"));
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use crate::db::CancelToken;
+
+/// How often a request waiting for a lease checks whether it was cancelled.
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The views of a parameterized source read the data of one combination of
+/// parameter values at a time. A lease keeps them on the values of the
+/// requests holding it: requests with the same values share it, while those
+/// with other values wait until it is released.
+#[derive(Default)]
+pub struct Leases {
+    sources: Mutex<HashMap<String, LeaseState>>,
+    released: Condvar,
+}
+
+#[derive(Default)]
+struct LeaseState {
+    /// Identifies the parameter values of the holders.
+    key: String,
+    holders: usize,
+    /// Requests waiting for other values, which new holders of the current
+    /// ones let go first so that they do not wait forever.
+    waiting: usize,
+}
+
+/// Held while a request reads a parameterized source, released on drop.
+pub struct Lease {
+    leases: Arc<Leases>,
+    source: String,
+}
+
+impl Leases {
+    /// Lease `source` for the parameter values identified by `key`, waiting
+    /// for holders of other values to release it.
+    pub fn acquire(
+        self: &Arc<Self>,
+        source: &str,
+        key: &str,
+        cancel: &CancelToken,
+    ) -> anyhow::Result<Lease> {
+        let mut sources = self.sources.lock().unwrap();
+        let mut waited = false;
+        loop {
+            let state = sources.entry(source.to_string()).or_default();
+            if waited {
+                state.waiting -= 1;
+            }
+            if state.holders == 0 || (state.key == key && state.waiting == 0) {
+                state.key = key.to_string();
+                state.holders += 1;
+                return Ok(Lease {
+                    leases: self.clone(),
+                    source: source.to_string(),
+                });
+            }
+            if cancel.is_cancelled() {
+                return Err(anyhow::anyhow!("Query was cancelled"));
+            }
+            state.waiting += 1;
+            waited = true;
+            sources = self
+                .released
+                .wait_timeout(sources, CANCEL_POLL_INTERVAL)
+                .unwrap()
+                .0;
+        }
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        let mut sources = self.leases.sources.lock().unwrap();
+        if let Some(state) = sources.get_mut(&self.source) {
+            state.holders -= 1;
+        }
+        self.leases.released.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn same_values_share_a_lease() {
+        let leases = Arc::new(Leases::default());
+        let cancel = CancelToken::default();
+        let _a = leases.acquire("logs", "day=1", &cancel).unwrap();
+        let _b = leases.acquire("logs", "day=1", &cancel).unwrap();
+        // Other sources are independent.
+        let _c = leases.acquire("users", "day=2", &cancel).unwrap();
+    }
+
+    #[test]
+    fn other_values_wait_until_released() {
+        let leases = Arc::new(Leases::default());
+        // Which values the source currently holds, and how many requests read
+        // them, as the views would.
+        let current = Arc::new(Mutex::new((String::new(), 0)));
+        let overlaps = Arc::new(AtomicUsize::new(0));
+
+        let threads = (0..8)
+            .map(|i| {
+                let leases = leases.clone();
+                let current = current.clone();
+                let overlaps = overlaps.clone();
+                std::thread::spawn(move || {
+                    let key = format!("day={}", i % 2);
+                    for _ in 0..20 {
+                        let _lease = leases
+                            .acquire("logs", &key, &CancelToken::default())
+                            .unwrap();
+                        {
+                            let mut current = current.lock().unwrap();
+                            if current.1 > 0 && current.0 != key {
+                                overlaps.fetch_add(1, Ordering::SeqCst);
+                            }
+                            current.0 = key.clone();
+                            current.1 += 1;
+                        }
+                        std::thread::sleep(Duration::from_micros(200));
+                        current.lock().unwrap().1 -= 1;
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(overlaps.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn waiting_is_cancellable() {
+        let leases = Arc::new(Leases::default());
+        let _held = leases
+            .acquire("logs", "day=1", &CancelToken::default())
+            .unwrap();
+        let cancel = CancelToken::default();
+        cancel.cancel();
+
+        let result = leases.acquire("logs", "day=2", &cancel);
+
+        assert!(result.is_err());
+    }
+}
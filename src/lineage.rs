@@ -0,0 +1,558 @@
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+
+use serde::{Deserialize, Serialize};
+use sqlparser::ast::{
+    visit_expressions, ExcludeSelectItem, Expr, Ident, Query, RenameSelectItem, Select, SelectItem,
+    SelectItemQualifiedWildcardKind, SetExpr, Statement, TableAlias, TableFactor, TableWithJoins,
+    WildcardAdditionalOptions,
+};
+use sqlparser::dialect::DuckDbDialect;
+use sqlparser::parser::Parser;
+use ts_rs::TS;
+
+use crate::deps::normalize_name;
+
+#[derive(TS, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum LineageKind {
+    /// The value is copied unchanged from the upstream column.
+    PassThrough,
+    /// The value is computed from the upstream column, e.g. by an expression
+    /// or an aggregate.
+    Derived,
+}
+
+#[derive(TS, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UpstreamColumn {
+    /// The source table (or initializer table) the column belongs to.
+    pub table: String,
+    pub column: String,
+    pub kind: LineageKind,
+}
+
+#[derive(TS, Serialize, Deserialize, Debug, Clone)]
+pub struct ColumnLineage {
+    pub column: String,
+    pub upstream: Vec<UpstreamColumn>,
+}
+
+/// What lineage is traced back to: the columns of every table that is not a
+/// query, and the SQL of every query, both keyed by normalized name.
+#[derive(Debug, Default)]
+pub struct Catalog {
+    pub tables: HashMap<String, Vec<String>>,
+    pub queries: HashMap<String, String>,
+}
+
+/// Output columns of a relation, each with its upstream columns.
+type Columns = Vec<(String, Vec<UpstreamColumn>)>;
+
+/// A relation in the `FROM` clause of a `SELECT`.
+struct Relation {
+    alias: String,
+    columns: Columns,
+}
+
+/// Trace every output column of the query `sql` back to the columns of the
+/// tables in `catalog`, following references to other queries and CTEs.
+///
+/// This is a best-effort static analysis: columns whose origin cannot be
+/// determined (e.g. read from a table function) have no upstream columns.
+pub fn analyze(sql: &str, catalog: &Catalog) -> anyhow::Result<Vec<ColumnLineage>> {
+    let columns = Analyzer::new(catalog).sql(sql)?;
+    Ok(columns
+        .into_iter()
+        .map(|(column, upstream)| ColumnLineage { column, upstream })
+        .collect())
+}
+
+struct Analyzer<'a> {
+    catalog: &'a Catalog,
+    /// CTEs visible at the current point, innermost `WITH` last.
+    ctes: Vec<HashMap<String, Columns>>,
+    /// Queries being analyzed, to stop on cycles.
+    resolving: Vec<String>,
+}
+
+impl<'a> Analyzer<'a> {
+    fn new(catalog: &'a Catalog) -> Self {
+        Self {
+            catalog,
+            ctes: vec![],
+            resolving: vec![],
+        }
+    }
+
+    fn sql(&mut self, sql: &str) -> anyhow::Result<Columns> {
+        let statements = Parser::parse_sql(&DuckDbDialect {}, sql)?;
+        match statements.as_slice() {
+            [Statement::Query(query)] => Ok(self.query(query)),
+            _ => Err(anyhow::anyhow!("Expected a single SELECT statement")),
+        }
+    }
+
+    fn query(&mut self, query: &Query) -> Columns {
+        self.ctes.push(HashMap::new());
+        if let Some(with) = &query.with {
+            for cte in with.cte_tables.iter() {
+                let columns = with_alias_columns(self.query(&cte.query), Some(&cte.alias));
+                self.ctes
+                    .last_mut()
+                    .unwrap()
+                    .insert(normalize_name(&cte.alias.name.value), columns);
+            }
+        }
+        let columns = self.set_expr(&query.body);
+        self.ctes.pop();
+        columns
+    }
+
+    fn set_expr(&mut self, expr: &SetExpr) -> Columns {
+        match expr {
+            SetExpr::Select(select) => self.select(select),
+            SetExpr::Query(query) => self.query(query),
+            SetExpr::SetOperation { left, right, .. } => {
+                // Columns are matched by position and named after the left
+                // side.
+                let mut columns = self.set_expr(left);
+                for ((_, upstream), (_, other)) in columns.iter_mut().zip(self.set_expr(right)) {
+                    for column in other {
+                        if !upstream.contains(&column) {
+                            upstream.push(column);
+                        }
+                    }
+                }
+                columns
+            }
+            _ => vec![],
+        }
+    }
+
+    /// The columns of the table, CTE or query called `name`.
+    fn table(&mut self, name: &str) -> Columns {
+        if let Some(columns) = self.ctes.iter().rev().find_map(|ctes| ctes.get(name)) {
+            return columns.clone();
+        }
+
+        if let Some(sql) = self.catalog.queries.get(name) {
+            if self.resolving.iter().any(|n| n == name) {
+                return vec![];
+            }
+            // The CTEs of the referencing query are not visible in another
+            // query.
+            let ctes = std::mem::take(&mut self.ctes);
+            self.resolving.push(name.to_string());
+            let columns = self.sql(sql).unwrap_or_default();
+            self.resolving.pop();
+            self.ctes = ctes;
+            return columns;
+        }
+
+        self.catalog
+            .tables
+            .get(name)
+            .map(|columns| {
+                columns
+                    .iter()
+                    .map(|column| {
+                        let upstream = UpstreamColumn {
+                            table: name.to_string(),
+                            column: column.clone(),
+                            kind: LineageKind::PassThrough,
+                        };
+                        (column.clone(), vec![upstream])
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn table_factor(&mut self, factor: &TableFactor, relations: &mut Vec<Relation>) {
+        match factor {
+            TableFactor::Table {
+                name,
+                alias,
+                args: None,
+                ..
+            } => {
+                let Some(ident) = name.0.last().and_then(|part| part.as_ident()) else {
+                    return;
+                };
+                let name = normalize_name(&ident.value);
+                let columns = with_alias_columns(self.table(&name), alias.as_ref());
+                relations.push(Relation {
+                    alias: alias
+                        .as_ref()
+                        .map(|alias| normalize_name(&alias.name.value))
+                        .unwrap_or(name),
+                    columns,
+                });
+            }
+            TableFactor::Derived {
+                subquery, alias, ..
+            } => {
+                let columns = with_alias_columns(self.query(subquery), alias.as_ref());
+                relations.push(Relation {
+                    alias: alias
+                        .as_ref()
+                        .map(|alias| normalize_name(&alias.name.value))
+                        .unwrap_or_default(),
+                    columns,
+                });
+            }
+            TableFactor::NestedJoin {
+                table_with_joins,
+                alias,
+            } => {
+                let mut nested = vec![];
+                self.table_with_joins(table_with_joins, &mut nested);
+                match alias {
+                    Some(alias) => relations.push(Relation {
+                        alias: normalize_name(&alias.name.value),
+                        columns: with_alias_columns(
+                            nested.into_iter().flat_map(|r| r.columns).collect(),
+                            Some(alias),
+                        ),
+                    }),
+                    None => relations.extend(nested),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn table_with_joins(&mut self, table: &TableWithJoins, relations: &mut Vec<Relation>) {
+        self.table_factor(&table.relation, relations);
+        for join in table.joins.iter() {
+            self.table_factor(&join.relation, relations);
+        }
+    }
+
+    fn select(&mut self, select: &Select) -> Columns {
+        let mut relations = vec![];
+        for table in select.from.iter() {
+            self.table_with_joins(table, &mut relations);
+        }
+
+        let mut columns = vec![];
+        for item in select.projection.iter() {
+            match item {
+                SelectItem::UnnamedExpr(expr) => {
+                    columns.push((expr_name(expr), expr_lineage(expr, &relations)));
+                }
+                SelectItem::ExprWithAlias { expr, alias } => {
+                    columns.push((alias.value.clone(), expr_lineage(expr, &relations)));
+                }
+                SelectItem::Wildcard(options) => {
+                    let all = relations.iter().flat_map(|r| r.columns.iter().cloned());
+                    columns.extend(wildcard(all.collect(), options, &relations));
+                }
+                SelectItem::QualifiedWildcard(
+                    SelectItemQualifiedWildcardKind::ObjectName(name),
+                    options,
+                ) => {
+                    let Some(ident) = name.0.last().and_then(|part| part.as_ident()) else {
+                        continue;
+                    };
+                    let alias = normalize_name(&ident.value);
+                    if let Some(relation) = relations.iter().find(|r| r.alias == alias) {
+                        columns.extend(wildcard(relation.columns.clone(), options, &relations));
+                    }
+                }
+                SelectItem::QualifiedWildcard(SelectItemQualifiedWildcardKind::Expr(_), _) => {}
+            }
+        }
+        columns
+    }
+}
+
+/// Apply the column names of `alias`, as in `FROM t AS u(a, b)`.
+fn with_alias_columns(mut columns: Columns, alias: Option<&TableAlias>) -> Columns {
+    if let Some(alias) = alias {
+        for ((name, _), column) in columns.iter_mut().zip(alias.columns.iter()) {
+            *name = column.name.value.clone();
+        }
+    }
+    columns
+}
+
+/// Expand a wildcard over `columns`, applying its `EXCLUDE`, `REPLACE` and
+/// `RENAME` options.
+fn wildcard(
+    columns: Columns,
+    options: &WildcardAdditionalOptions,
+    relations: &[Relation],
+) -> Columns {
+    let excluded: Vec<String> = match &options.opt_exclude {
+        Some(ExcludeSelectItem::Single(ident)) => vec![normalize_name(&ident.value)],
+        Some(ExcludeSelectItem::Multiple(idents)) => {
+            idents.iter().map(|i| normalize_name(&i.value)).collect()
+        }
+        None => vec![],
+    };
+    let renames: Vec<(String, String)> = match &options.opt_rename {
+        Some(RenameSelectItem::Single(item)) => {
+            vec![(normalize_name(&item.ident.value), item.alias.value.clone())]
+        }
+        Some(RenameSelectItem::Multiple(items)) => items
+            .iter()
+            .map(|item| (normalize_name(&item.ident.value), item.alias.value.clone()))
+            .collect(),
+        None => vec![],
+    };
+
+    columns
+        .into_iter()
+        .filter(|(name, _)| !excluded.contains(&normalize_name(name)))
+        .map(|(name, upstream)| {
+            let key = normalize_name(&name);
+            let replacement = options.opt_replace.as_ref().and_then(|replace| {
+                replace
+                    .items
+                    .iter()
+                    .find(|item| normalize_name(&item.column_name.value) == key)
+            });
+            let upstream = match replacement {
+                Some(item) => expr_lineage(&item.expr, relations),
+                None => upstream,
+            };
+            let name = renames
+                .iter()
+                .find(|(old, _)| *old == key)
+                .map(|(_, new)| new.clone())
+                .unwrap_or(name);
+            (name, upstream)
+        })
+        .collect()
+}
+
+/// The name DuckDB gives an unaliased expression, near enough.
+fn expr_name(expr: &Expr) -> String {
+    match expr {
+        Expr::Identifier(ident) => ident.value.clone(),
+        Expr::CompoundIdentifier(idents) => idents.last().unwrap().value.clone(),
+        Expr::Nested(expr) => expr_name(expr),
+        _ => expr.to_string(),
+    }
+}
+
+/// The upstream columns of a column reference, and whether it refers to the
+/// whole column rather than a field inside it.
+fn resolve(idents: &[Ident], relations: &[Relation]) -> Option<(Vec<UpstreamColumn>, bool)> {
+    let parts = idents
+        .iter()
+        .map(|i| normalize_name(&i.value))
+        .collect::<Vec<_>>();
+    let find = |relation: &Relation, column: &str| {
+        relation
+            .columns
+            .iter()
+            .find(|(name, _)| normalize_name(name) == column)
+            .map(|(_, upstream)| upstream.clone())
+    };
+
+    // `alias.column[.field...]`
+    if parts.len() >= 2 {
+        if let Some(upstream) = relations
+            .iter()
+            .filter(|r| r.alias == parts[0])
+            .find_map(|r| find(r, &parts[1]))
+        {
+            return Some((upstream, parts.len() == 2));
+        }
+    }
+    // `column[.field...]`
+    relations
+        .iter()
+        .find_map(|r| find(r, &parts[0]))
+        .map(|upstream| (upstream, parts.len() == 1))
+}
+
+fn expr_lineage(expr: &Expr, relations: &[Relation]) -> Vec<UpstreamColumn> {
+    let idents = match expr {
+        Expr::Nested(expr) => return expr_lineage(expr, relations),
+        Expr::Identifier(ident) => Some(vec![ident.clone()]),
+        Expr::CompoundIdentifier(idents) => Some(idents.clone()),
+        _ => None,
+    };
+    if let Some((upstream, true)) = idents.and_then(|idents| resolve(&idents, relations)) {
+        return upstream;
+    }
+
+    let mut upstream: Vec<UpstreamColumn> = vec![];
+    let _ = visit_expressions(expr, |expr| {
+        let idents = match expr {
+            Expr::Identifier(ident) => vec![ident.clone()],
+            Expr::CompoundIdentifier(idents) => idents.clone(),
+            _ => return ControlFlow::<()>::Continue(()),
+        };
+        for mut column in resolve(&idents, relations).map(|r| r.0).unwrap_or_default() {
+            column.kind = LineageKind::Derived;
+            if !upstream.contains(&column) {
+                upstream.push(column);
+            }
+        }
+        ControlFlow::Continue(())
+    });
+    upstream
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalog() -> Catalog {
+        let table = |columns: &[&str]| columns.iter().map(|c| c.to_string()).collect();
+        Catalog {
+            tables: HashMap::from([
+                ("users".to_string(), table(&["id", "name", "age"])),
+                ("orders".to_string(), table(&["id", "user_id", "amount"])),
+            ]),
+            queries: HashMap::from([(
+                "big_orders".to_string(),
+                "SELECT id, amount * 2 AS doubled FROM orders WHERE amount > 10".to_string(),
+            )]),
+        }
+    }
+
+    /// Each output column with its upstream columns as `table.column`,
+    /// prefixed with `~` when derived.
+    fn lineage(sql: &str) -> Vec<(String, Vec<String>)> {
+        analyze(sql, &catalog())
+            .unwrap()
+            .into_iter()
+            .map(|column| {
+                let upstream = column
+                    .upstream
+                    .iter()
+                    .map(|upstream| {
+                        let prefix = match upstream.kind {
+                            LineageKind::PassThrough => "",
+                            LineageKind::Derived => "~",
+                        };
+                        format!("{}{}.{}", prefix, upstream.table, upstream.column)
+                    })
+                    .collect();
+                (column.column, upstream)
+            })
+            .collect()
+    }
+
+    fn expected(columns: &[(&str, &[&str])]) -> Vec<(String, Vec<String>)> {
+        columns
+            .iter()
+            .map(|(column, upstream)| {
+                let upstream = upstream.iter().map(|u| u.to_string()).collect();
+                (column.to_string(), upstream)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn aliases_and_expressions() {
+        assert_eq!(
+            lineage("SELECT u.id AS user_id, upper(u.name) AS name, age FROM users AS u"),
+            expected(&[
+                ("user_id", &["users.id"]),
+                ("name", &["~users.name"]),
+                ("age", &["users.age"]),
+            ])
+        );
+    }
+
+    #[test]
+    fn joins() {
+        assert_eq!(
+            lineage(
+                "SELECT u.name, o.amount, user_id, o.amount / u.age AS ratio \
+                 FROM users u JOIN orders o ON o.user_id = u.id"
+            ),
+            expected(&[
+                ("name", &["users.name"]),
+                ("amount", &["orders.amount"]),
+                ("user_id", &["orders.user_id"]),
+                ("ratio", &["~orders.amount", "~users.age"]),
+            ])
+        );
+    }
+
+    #[test]
+    fn ctes() {
+        assert_eq!(
+            lineage(
+                "WITH t AS (SELECT id, age * 2 AS a FROM users), \
+                 renamed(x) AS (SELECT id FROM t) \
+                 SELECT t.a, renamed.x FROM t JOIN renamed ON t.id = renamed.x"
+            ),
+            expected(&[("a", &["~users.age"]), ("x", &["users.id"])])
+        );
+    }
+
+    #[test]
+    fn subqueries() {
+        assert_eq!(
+            lineage(
+                "SELECT s.user_id, s.total FROM \
+                 (SELECT user_id, sum(amount) AS total FROM orders GROUP BY user_id) AS s"
+            ),
+            expected(&[
+                ("user_id", &["orders.user_id"]),
+                ("total", &["~orders.amount"])
+            ])
+        );
+    }
+
+    #[test]
+    fn other_queries() {
+        assert_eq!(
+            lineage("SELECT id, doubled FROM big_orders"),
+            expected(&[("id", &["orders.id"]), ("doubled", &["~orders.amount"])])
+        );
+    }
+
+    #[test]
+    fn wildcards() {
+        assert_eq!(
+            lineage("SELECT * FROM users"),
+            expected(&[
+                ("id", &["users.id"]),
+                ("name", &["users.name"]),
+                ("age", &["users.age"]),
+            ])
+        );
+        assert_eq!(
+            lineage("SELECT o.*, u.name FROM orders o JOIN users u ON o.user_id = u.id"),
+            expected(&[
+                ("id", &["orders.id"]),
+                ("user_id", &["orders.user_id"]),
+                ("amount", &["orders.amount"]),
+                ("name", &["users.name"]),
+            ])
+        );
+        assert_eq!(
+            lineage("SELECT * EXCLUDE (age) REPLACE (upper(name) AS name) FROM users"),
+            expected(&[("id", &["users.id"]), ("name", &["~users.name"])])
+        );
+    }
+
+    #[test]
+    fn unions_merge_upstream_columns() {
+        assert_eq!(
+            lineage("SELECT id FROM users UNION ALL SELECT user_id FROM orders"),
+            expected(&[("id", &["users.id", "orders.user_id"])])
+        );
+    }
+
+    #[test]
+    fn unknown_tables_have_no_upstream() {
+        assert_eq!(
+            lineage("SELECT x FROM read_csv('a.csv')"),
+            expected(&[("x", &[])])
+        );
+    }
+
+    #[test]
+    fn rejects_other_statements() {
+        assert!(analyze("DELETE FROM users", &catalog()).is_err());
+    }
+}
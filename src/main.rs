@@ -1,15 +1,59 @@
-use open;
+use std::ffi::OsString;
+
+use clap::Parser;
 use tokio::time::{sleep, Duration};
 
+mod aggregate;
 mod api;
+mod cache;
 mod cli_config;
 mod config;
+mod cursor;
 mod db;
+mod deps;
+mod filter;
+mod graph;
+mod http;
 mod json;
+mod leases;
+mod lineage;
+mod pool;
+mod rejects;
+mod schema;
+mod summary;
+mod text;
+
+async fn print_graph(args: Vec<OsString>) -> anyhow::Result<()> {
+    // `graph` and its options come before the first `--`, the config after.
+    let split_at = args
+        .iter()
+        .position(|arg| arg == "--")
+        .unwrap_or(args.len());
+    let command = cli_config::GraphCommand::parse_from(&args[1..split_at]);
+
+    let mut config_args = vec![args[0].clone()];
+    config_args.extend(args.into_iter().skip(split_at + 1));
+    let db = db::DB::new_from_cli_args(config_args).await?;
+
+    let graph = db.dependency_graph()?;
+    match command.format {
+        cli_config::GraphFormat::Json => println!("{}", serde_json::to_string_pretty(&graph)?),
+        cli_config::GraphFormat::Dot => print!("{}", graph.to_dot()),
+    }
+    Ok(())
+}
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let db = db::DB::new_from_cli_args().await?;
+    let args: Vec<OsString> = std::env::args_os().collect();
+    if args.get(1).is_some_and(|arg| arg == "graph") {
+        print_graph(args).await?;
+        // Exit right away rather than waiting on the file watcher, which
+        // never finishes.
+        std::process::exit(0);
+    }
+
+    let db = db::DB::new_from_cli_args(args).await?;
 
     let should_open = db.config.open;
 
@@ -0,0 +1,175 @@
+use std::ops::Deref;
+use std::sync::{Condvar, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError};
+use std::time::Duration;
+
+use duckdb::Connection;
+
+use crate::db::CancelToken;
+
+/// How often a change to the catalog checks whether the queries it waits for
+/// have finished, or it was cancelled.
+const WRITE_RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A fixed set of connections to the same DuckDB database, so that queries
+/// can run concurrently.
+///
+/// Changes to the catalog (creating or replacing views, tables) go through
+/// `write`, which waits until no query is running and keeps new ones out
+/// until it is dropped. A batch of changes made with one `write` connection is
+/// therefore never seen half-applied.
+pub struct Pool {
+    idle: Mutex<Vec<Connection>>,
+    available: Condvar,
+    catalog: RwLock<()>,
+    /// How many `write` calls are waiting for the running queries to finish.
+    /// New queries wait for them, so that a steady stream of queries cannot
+    /// hold a change off forever.
+    waiting_writers: Mutex<usize>,
+    writers_done: Condvar,
+}
+
+enum CatalogGuard<'a> {
+    Read(#[allow(dead_code)] RwLockReadGuard<'a, ()>),
+    Write(#[allow(dead_code)] RwLockWriteGuard<'a, ()>),
+}
+
+/// A connection borrowed from the pool, returned to it when dropped.
+pub struct PooledConnection<'a> {
+    pool: &'a Pool,
+    conn: Option<Connection>,
+    // Dropped after the connection is returned, see `Drop`.
+    _catalog: CatalogGuard<'a>,
+}
+
+impl Pool {
+    pub fn new(conn: Connection, size: usize) -> anyhow::Result<Self> {
+        let mut idle = vec![];
+        for _ in 1..size.max(1) {
+            idle.push(conn.try_clone()?);
+        }
+        idle.push(conn);
+        Ok(Self {
+            idle: Mutex::new(idle),
+            available: Condvar::new(),
+            catalog: RwLock::new(()),
+            waiting_writers: Mutex::new(0),
+            writers_done: Condvar::new(),
+        })
+    }
+
+    /// A connection for running queries, once pending changes to the catalog
+    /// are made.
+    pub fn read(&self) -> PooledConnection<'_> {
+        let mut waiting_writers = self.waiting_writers.lock().unwrap();
+        while *waiting_writers > 0 {
+            waiting_writers = self.writers_done.wait(waiting_writers).unwrap();
+        }
+        drop(waiting_writers);
+        let guard = CatalogGuard::Read(self.catalog.read().unwrap());
+        self.checkout(guard)
+    }
+
+    /// A connection for changing the catalog, with exclusive access to it.
+    ///
+    /// New queries wait while this waits for the running ones to finish. Fails
+    /// if `cancel` is cancelled in the meantime, which lets them through
+    /// again.
+    pub fn write(&self, cancel: &CancelToken) -> anyhow::Result<PooledConnection<'_>> {
+        *self.waiting_writers.lock().unwrap() += 1;
+        let guard = loop {
+            match self.catalog.try_write() {
+                Ok(guard) => break Ok(guard),
+                Err(TryLockError::WouldBlock) => {
+                    if cancel.is_cancelled() {
+                        break Err(anyhow::anyhow!("Query was cancelled"));
+                    }
+                    std::thread::sleep(WRITE_RETRY_INTERVAL);
+                }
+                Err(TryLockError::Poisoned(e)) => panic!("{}", e),
+            }
+        };
+        *self.waiting_writers.lock().unwrap() -= 1;
+        self.writers_done.notify_all();
+        Ok(self.checkout(CatalogGuard::Write(guard?)))
+    }
+
+    fn checkout<'a>(&'a self, guard: CatalogGuard<'a>) -> PooledConnection<'a> {
+        let mut idle = self.idle.lock().unwrap();
+        let conn = loop {
+            match idle.pop() {
+                Some(conn) => break conn,
+                None => idle = self.available.wait(idle).unwrap(),
+            }
+        };
+        PooledConnection {
+            pool: self,
+            conn: Some(conn),
+            _catalog: guard,
+        }
+    }
+}
+
+impl Deref for PooledConnection<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().unwrap()
+    }
+}
+
+impl Drop for PooledConnection<'_> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            self.pool.idle.lock().unwrap().push(conn);
+            self.pool.available.notify_one();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool() -> Pool {
+        Pool::new(Connection::open_in_memory().unwrap(), 4).unwrap()
+    }
+
+    #[test]
+    fn waiting_to_write_holds_up_new_readers() {
+        let pool = pool();
+        let running = pool.read();
+        let cancel = CancelToken::default();
+
+        std::thread::scope(|scope| {
+            let writer = scope.spawn(|| pool.write(&cancel).map(|_| ()));
+            std::thread::sleep(Duration::from_millis(50));
+            // With the writer waiting, a new query waits for it too.
+            let reader = scope.spawn(|| drop(pool.read()));
+            std::thread::sleep(Duration::from_millis(50));
+            assert!(!reader.is_finished());
+
+            drop(running);
+            assert!(writer.join().unwrap().is_ok());
+            reader.join().unwrap();
+        });
+    }
+
+    #[test]
+    fn cancelling_a_waiting_writer_lets_readers_through() {
+        let pool = pool();
+        let running = pool.read();
+        let cancel = CancelToken::default();
+
+        std::thread::scope(|scope| {
+            let writer = scope.spawn(|| pool.write(&cancel).map(|_| ()));
+            std::thread::sleep(Duration::from_millis(50));
+            let reader = scope.spawn(|| drop(pool.read()));
+            cancel.cancel();
+            assert!(writer.join().unwrap().is_err());
+            reader.join().unwrap();
+        });
+
+        drop(running);
+        assert!(pool.write(&CancelToken::default()).is_ok());
+    }
+}
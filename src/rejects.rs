@@ -0,0 +1,121 @@
+use std::path::Path;
+
+use serde_json::Deserializer;
+
+/// A record that `read_json(..., ignore_errors = true)` silently drops.
+#[derive(Debug, Clone)]
+pub struct Reject {
+    /// 1-based line on which the malformed record starts.
+    pub line: u64,
+    pub error: String,
+    pub raw: String,
+}
+
+/// Find the malformed records in a JSON file.
+///
+/// Sources are read with `format = 'unstructured'`, so a file can hold a
+/// single document, newline-delimited records, or records spread over
+/// several lines. The file is parsed as a stream of values; after an error,
+/// parsing resumes on the next line, which is where DuckDB picks up again for
+/// newline-delimited input. A malformed top-level array is reported once,
+/// since nothing after the error can be recovered. Invalid UTF-8 is a
+/// malformed record like any other, rather than failing the scan.
+pub fn scan_json_file(path: &Path) -> anyhow::Result<Vec<Reject>> {
+    let bytes = std::fs::read(path)?;
+    let is_array = bytes.trim_ascii_start().starts_with(b"[");
+
+    let mut rejects = vec![];
+    let mut offset = 0;
+    let mut base_line = 0;
+
+    loop {
+        let slice = &bytes[offset..];
+        let mut stream = Deserializer::from_slice(slice).into_iter::<serde_json::Value>();
+        let error = loop {
+            match stream.next() {
+                None => break None,
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => break Some(e),
+            }
+        };
+
+        let Some(error) = error else {
+            break;
+        };
+
+        let line = base_line + error.line();
+        // The message ends in a position relative to the current slice, which
+        // would be misleading.
+        let message = error.to_string();
+        let message = match message.rsplit_once(" at line ") {
+            Some((message, _)) => message.to_string(),
+            None => message,
+        };
+        rejects.push(Reject {
+            line: line as u64,
+            error: message,
+            raw: bytes
+                .split(|b| *b == b'\n')
+                .nth(line.saturating_sub(1))
+                .map(|raw| String::from_utf8_lossy(raw.strip_suffix(b"\r").unwrap_or(raw)))
+                .unwrap_or_default()
+                .to_string(),
+        });
+
+        if is_array {
+            break;
+        }
+
+        match slice
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| **b == b'\n')
+            .nth(error.line().saturating_sub(1))
+        {
+            Some((idx, _)) => {
+                offset += idx + 1;
+                base_line = line;
+            }
+            None => break,
+        }
+    }
+
+    Ok(rejects)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(contents: &[u8]) -> Vec<Reject> {
+        let path = std::env::temp_dir().join(format!(
+            "frogtable-rejects-{}-{}.json",
+            std::process::id(),
+            contents.len()
+        ));
+        std::fs::write(&path, contents).unwrap();
+        let rejects = scan_json_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        rejects
+    }
+
+    #[test]
+    fn reports_each_malformed_record() {
+        let rejects = scan(b"{\"a\": 1}\n{\"a\": 2,}\n{\"a\": 3}\n{oops}\n");
+
+        assert_eq!(rejects.len(), 2);
+        assert_eq!(rejects[0].line, 2);
+        assert_eq!(rejects[0].raw, "{\"a\": 2,}");
+        assert_eq!(rejects[1].line, 4);
+        assert_eq!(rejects[1].raw, "{oops}");
+    }
+
+    #[test]
+    fn invalid_utf8_is_a_reject() {
+        let rejects = scan(b"{\"a\": \"ok\"}\n{\"a\": \"\xff\xfe\"}\n{\"a\": \"ok\"}\n");
+
+        assert_eq!(rejects.len(), 1);
+        assert_eq!(rejects[0].line, 2);
+        assert_eq!(rejects[0].raw, "{\"a\": \"\u{fffd}\u{fffd}\"}");
+    }
+}
@@ -0,0 +1,556 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+/// A column of a query, as described by DuckDB.
+#[derive(TS, Serialize, Deserialize, Debug, Clone)]
+pub struct ColumnSchema {
+    pub name: String,
+    /// The type as DuckDB names it, e.g. `STRUCT(x INTEGER, y VARCHAR[])`.
+    pub data_type: String,
+    pub column_type: ColumnType,
+    pub nullable: bool,
+    /// Set with `COMMENT ON COLUMN`.
+    pub comment: Option<String>,
+}
+
+/// A DuckDB logical type.
+#[derive(TS, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum ColumnType {
+    Boolean,
+    TinyInt,
+    SmallInt,
+    Integer,
+    BigInt,
+    HugeInt,
+    UTinyInt,
+    USmallInt,
+    UInteger,
+    UBigInt,
+    UHugeInt,
+    VarInt,
+    Float,
+    Double,
+    Decimal {
+        precision: u8,
+        scale: u8,
+    },
+    Varchar,
+    Blob,
+    Bit,
+    Uuid,
+    Date,
+    Time {
+        with_time_zone: bool,
+    },
+    Timestamp {
+        unit: TimeUnit,
+        /// The time zone values are given in, for `TIMESTAMP WITH TIME ZONE`.
+        time_zone: Option<String>,
+    },
+    Interval,
+    Enum {
+        values: Vec<String>,
+    },
+    List {
+        element: Box<ColumnType>,
+    },
+    /// A list of fixed size.
+    Array {
+        element: Box<ColumnType>,
+        size: u32,
+    },
+    Struct {
+        fields: Vec<Field>,
+    },
+    Map {
+        key: Box<ColumnType>,
+        value: Box<ColumnType>,
+    },
+    Union {
+        members: Vec<Field>,
+    },
+    /// Any other type, such as those of extensions.
+    Other {
+        name: String,
+    },
+}
+
+#[derive(TS, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum TimeUnit {
+    Second,
+    Millisecond,
+    Microsecond,
+    Nanosecond,
+}
+
+/// A field of a struct, or a member of a union.
+#[derive(TS, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Field {
+    pub name: String,
+    pub column_type: ColumnType,
+}
+
+impl ColumnType {
+    /// Parse a type as DuckDB prints it, e.g. in `DESCRIBE`. Types that cannot
+    /// be parsed are kept as `Other`.
+    pub fn parse(name: &str) -> Self {
+        let mut parser = TypeParser {
+            input: name,
+            pos: 0,
+        };
+        match parser.parse_type() {
+            Some(column_type) if parser.at_end() => column_type,
+            _ => ColumnType::Other {
+                name: name.to_string(),
+            },
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            ColumnType::TinyInt
+                | ColumnType::SmallInt
+                | ColumnType::Integer
+                | ColumnType::BigInt
+                | ColumnType::HugeInt
+                | ColumnType::UTinyInt
+                | ColumnType::USmallInt
+                | ColumnType::UInteger
+                | ColumnType::UBigInt
+                | ColumnType::UHugeInt
+                | ColumnType::VarInt
+                | ColumnType::Float
+                | ColumnType::Double
+                | ColumnType::Decimal { .. }
+        )
+    }
+}
+
+struct TypeParser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> TypeParser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip_whitespace();
+        self.rest().is_empty()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Option<()> {
+        self.eat(c).then_some(())
+    }
+
+    fn word(&mut self) -> Option<&'a str> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return None;
+        }
+        self.pos += len;
+        Some(&rest[..len])
+    }
+
+    /// Consume `words` if they come next, ignoring case.
+    fn eat_words(&mut self, words: &[&str]) -> bool {
+        let start = self.pos;
+        for expected in words {
+            if !self
+                .word()
+                .is_some_and(|word| word.eq_ignore_ascii_case(expected))
+            {
+                self.pos = start;
+                return false;
+            }
+        }
+        true
+    }
+
+    fn number<T: std::str::FromStr>(&mut self) -> Option<T> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        self.pos += len;
+        rest[..len].parse().ok()
+    }
+
+    /// A string delimited by `quote`, in which the quote is escaped by
+    /// doubling it.
+    fn quoted(&mut self, quote: char) -> Option<String> {
+        self.expect(quote)?;
+        let mut value = String::new();
+        let mut chars = self.rest().char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            if c == quote {
+                if chars.peek().is_some_and(|(_, next)| *next == quote) {
+                    chars.next();
+                } else {
+                    self.pos += i + 1;
+                    return Some(value);
+                }
+            }
+            value.push(c);
+        }
+        None
+    }
+
+    fn name(&mut self) -> Option<String> {
+        self.skip_whitespace();
+        if self.rest().starts_with('"') {
+            self.quoted('"')
+        } else {
+            self.word().map(str::to_string)
+        }
+    }
+
+    /// `name TYPE, ...)`, after the opening parenthesis.
+    fn fields(&mut self) -> Option<Vec<Field>> {
+        let mut fields = vec![];
+        loop {
+            let name = self.name()?;
+            let column_type = self.parse_type()?;
+            fields.push(Field { name, column_type });
+            if !self.eat(',') {
+                break;
+            }
+        }
+        self.expect(')')?;
+        Some(fields)
+    }
+
+    fn parse_type(&mut self) -> Option<ColumnType> {
+        let mut column_type = self.parse_base_type()?;
+        // `INTEGER[]` is a list, `INTEGER[3]` an array.
+        while self.eat('[') {
+            column_type = if self.eat(']') {
+                ColumnType::List {
+                    element: Box::new(column_type),
+                }
+            } else {
+                let size = self.number()?;
+                self.expect(']')?;
+                ColumnType::Array {
+                    element: Box::new(column_type),
+                    size,
+                }
+            };
+        }
+        Some(column_type)
+    }
+
+    fn parse_base_type(&mut self) -> Option<ColumnType> {
+        let start = self.pos;
+        let name = self.word()?.to_uppercase();
+        let timestamp = |unit| ColumnType::Timestamp {
+            unit,
+            time_zone: None,
+        };
+        let with_time_zone = ColumnType::Timestamp {
+            unit: TimeUnit::Microsecond,
+            time_zone: Some("UTC".to_string()),
+        };
+        Some(match name.as_str() {
+            "BOOLEAN" | "BOOL" | "LOGICAL" => ColumnType::Boolean,
+            "TINYINT" | "INT1" => ColumnType::TinyInt,
+            "SMALLINT" | "INT2" | "SHORT" => ColumnType::SmallInt,
+            "INTEGER" | "INT" | "INT4" | "SIGNED" => ColumnType::Integer,
+            "BIGINT" | "INT8" | "LONG" => ColumnType::BigInt,
+            "HUGEINT" | "INT128" => ColumnType::HugeInt,
+            "UTINYINT" => ColumnType::UTinyInt,
+            "USMALLINT" => ColumnType::USmallInt,
+            "UINTEGER" => ColumnType::UInteger,
+            "UBIGINT" => ColumnType::UBigInt,
+            "UHUGEINT" => ColumnType::UHugeInt,
+            "VARINT" => ColumnType::VarInt,
+            "FLOAT" | "FLOAT4" | "REAL" => ColumnType::Float,
+            "DOUBLE" | "FLOAT8" => ColumnType::Double,
+            "DECIMAL" | "NUMERIC" => {
+                // DuckDB's default precision and scale.
+                let (mut precision, mut scale) = (18, 3);
+                if self.eat('(') {
+                    precision = self.number()?;
+                    scale = if self.eat(',') { self.number()? } else { 0 };
+                    self.expect(')')?;
+                }
+                ColumnType::Decimal { precision, scale }
+            }
+            "VARCHAR" | "TEXT" | "STRING" | "CHAR" | "BPCHAR" => ColumnType::Varchar,
+            "BLOB" | "BYTEA" | "BINARY" | "VARBINARY" => ColumnType::Blob,
+            "BIT" | "BITSTRING" => ColumnType::Bit,
+            "UUID" => ColumnType::Uuid,
+            "DATE" => ColumnType::Date,
+            "TIME" => ColumnType::Time {
+                with_time_zone: self.eat_words(&["WITH", "TIME", "ZONE"]),
+            },
+            "TIMETZ" => ColumnType::Time {
+                with_time_zone: true,
+            },
+            "TIMESTAMP" | "DATETIME" => match self.eat_words(&["WITH", "TIME", "ZONE"]) {
+                true => with_time_zone,
+                false => timestamp(TimeUnit::Microsecond),
+            },
+            "TIMESTAMPTZ" => with_time_zone,
+            "TIMESTAMP_S" => timestamp(TimeUnit::Second),
+            "TIMESTAMP_MS" => timestamp(TimeUnit::Millisecond),
+            "TIMESTAMP_US" => timestamp(TimeUnit::Microsecond),
+            "TIMESTAMP_NS" => timestamp(TimeUnit::Nanosecond),
+            "INTERVAL" => ColumnType::Interval,
+            "ENUM" => {
+                self.expect('(')?;
+                let mut values = vec![];
+                loop {
+                    values.push(self.quoted('\'')?);
+                    if !self.eat(',') {
+                        break;
+                    }
+                }
+                self.expect(')')?;
+                ColumnType::Enum { values }
+            }
+            "STRUCT" | "ROW" => {
+                self.expect('(')?;
+                ColumnType::Struct {
+                    fields: self.fields()?,
+                }
+            }
+            "UNION" => {
+                self.expect('(')?;
+                ColumnType::Union {
+                    members: self.fields()?,
+                }
+            }
+            "MAP" => {
+                self.expect('(')?;
+                let key = self.parse_type()?;
+                self.expect(',')?;
+                let value = self.parse_type()?;
+                self.expect(')')?;
+                ColumnType::Map {
+                    key: Box::new(key),
+                    value: Box::new(value),
+                }
+            }
+            _ => {
+                // Keep any parameters, e.g. of extension types, in the name.
+                if self.eat('(') {
+                    let mut depth = 1;
+                    while depth > 0 {
+                        let c = self.rest().chars().next()?;
+                        self.pos += c.len_utf8();
+                        match c {
+                            '(' => depth += 1,
+                            ')' => depth -= 1,
+                            _ => {}
+                        }
+                    }
+                }
+                ColumnType::Other {
+                    name: self.input[start..self.pos].trim().to_string(),
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(name: &str, column_type: ColumnType) -> Field {
+        Field {
+            name: name.to_string(),
+            column_type,
+        }
+    }
+
+    fn list(element: ColumnType) -> ColumnType {
+        ColumnType::List {
+            element: Box::new(element),
+        }
+    }
+
+    #[test]
+    fn parses_nested_structs() {
+        assert_eq!(
+            ColumnType::parse(r#"STRUCT("a b" INTEGER, "it""s" STRUCT(x VARCHAR, y DOUBLE[]))"#),
+            ColumnType::Struct {
+                fields: vec![
+                    field("a b", ColumnType::Integer),
+                    field(
+                        "it\"s",
+                        ColumnType::Struct {
+                            fields: vec![
+                                field("x", ColumnType::Varchar),
+                                field("y", list(ColumnType::Double)),
+                            ]
+                        }
+                    ),
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn parses_lists_and_arrays() {
+        assert_eq!(
+            ColumnType::parse("INTEGER[3][]"),
+            list(ColumnType::Array {
+                element: Box::new(ColumnType::Integer),
+                size: 3,
+            })
+        );
+        assert_eq!(
+            ColumnType::parse("STRUCT(x INTEGER)[]"),
+            list(ColumnType::Struct {
+                fields: vec![field("x", ColumnType::Integer)]
+            })
+        );
+    }
+
+    #[test]
+    fn parses_maps_and_unions() {
+        assert_eq!(
+            ColumnType::parse("MAP(VARCHAR, MAP(INTEGER, BLOB[]))"),
+            ColumnType::Map {
+                key: Box::new(ColumnType::Varchar),
+                value: Box::new(ColumnType::Map {
+                    key: Box::new(ColumnType::Integer),
+                    value: Box::new(list(ColumnType::Blob)),
+                }),
+            }
+        );
+        assert_eq!(
+            ColumnType::parse(r#"UNION(num INTEGER, "str ing" VARCHAR)"#),
+            ColumnType::Union {
+                members: vec![
+                    field("num", ColumnType::Integer),
+                    field("str ing", ColumnType::Varchar),
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn parses_enums_with_escaped_quotes() {
+        assert_eq!(
+            ColumnType::parse(r#"ENUM('it''s ok', 'sad, "very"', '(')"#),
+            ColumnType::Enum {
+                values: vec![
+                    "it's ok".to_string(),
+                    "sad, \"very\"".to_string(),
+                    "(".to_string(),
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn parses_decimals() {
+        let decimal = |precision, scale| ColumnType::Decimal { precision, scale };
+
+        assert_eq!(ColumnType::parse("DECIMAL"), decimal(18, 3));
+        assert_eq!(ColumnType::parse("DECIMAL(10)"), decimal(10, 0));
+        assert_eq!(ColumnType::parse("NUMERIC(5, 2)"), decimal(5, 2));
+    }
+
+    #[test]
+    fn parses_times() {
+        assert_eq!(
+            ColumnType::parse("TIMESTAMP WITH TIME ZONE"),
+            ColumnType::Timestamp {
+                unit: TimeUnit::Microsecond,
+                time_zone: Some("UTC".to_string()),
+            }
+        );
+        assert_eq!(
+            ColumnType::parse("timestamp_ns"),
+            ColumnType::Timestamp {
+                unit: TimeUnit::Nanosecond,
+                time_zone: None,
+            }
+        );
+        assert_eq!(
+            ColumnType::parse("TIME WITH TIME ZONE"),
+            ColumnType::Time {
+                with_time_zone: true
+            }
+        );
+    }
+
+    #[test]
+    fn falls_back_to_other() {
+        let other = |name: &str| ColumnType::Other {
+            name: name.to_string(),
+        };
+
+        assert_eq!(ColumnType::parse("GEOMETRY"), other("GEOMETRY"));
+        assert_eq!(
+            ColumnType::parse("POINT_2D(x, y)[]"),
+            list(other("POINT_2D(x, y)"))
+        );
+        // Malformed types are kept whole.
+        assert_eq!(
+            ColumnType::parse("STRUCT(a INTEGER"),
+            other("STRUCT(a INTEGER")
+        );
+        assert_eq!(ColumnType::parse("INTEGER extra"), other("INTEGER extra"));
+        assert_eq!(ColumnType::parse("ENUM('a)"), other("ENUM('a)"));
+        assert_eq!(ColumnType::parse(""), other(""));
+    }
+
+    #[test]
+    fn parses_types_described_by_duckdb() {
+        let conn = duckdb::Connection::open_in_memory().unwrap();
+        let mut stmt = conn
+            .prepare(
+                r#"DESCRIBE SELECT
+                    {'a b': 1, 'c': [1.5::DECIMAL(5,2)]} AS s,
+                    [1, 2]::INTEGER[2] AS a,
+                    MAP {'k': [1]} AS m,
+                    union_value(num := 2)::UNION(num INTEGER, "str ing" VARCHAR) AS u,
+                    'x'::ENUM('it''s', 'x') AS e,
+                    now() AS t,
+                    '1 day'::INTERVAL AS i"#,
+            )
+            .unwrap();
+        let types = stmt
+            .query_map([], |row| row.get::<_, String>(1))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        for data_type in types {
+            assert!(
+                !matches!(ColumnType::parse(&data_type), ColumnType::Other { .. }),
+                "{} was not parsed",
+                data_type
+            );
+        }
+    }
+}
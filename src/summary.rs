@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::schema::ColumnType;
+
+pub const DEFAULT_TOP_K: u32 = 5;
+pub const DEFAULT_VALUES_LIMIT: u32 = 100;
+pub const DEFAULT_HISTOGRAM_BUCKETS: u32 = 10;
+
+/// Statistics of a column over the rows matching a request's filters.
+#[derive(TS, Serialize, Deserialize, Debug, Clone)]
+pub struct ColumnSummary {
+    pub name: String,
+    pub column_type: ColumnType,
+    /// The number of rows, nulls included.
+    pub count: u64,
+    pub null_count: u64,
+    /// `null_count` over `count`, 0 when there are no rows.
+    pub null_ratio: f64,
+    /// Estimated with HyperLogLog, so it can be off by a few percent.
+    pub approx_distinct: u64,
+    /// As DuckDB prints them, since not every type compares as JSON.
+    pub min: Option<String>,
+    pub max: Option<String>,
+    /// Only set for numbers.
+    pub mean: Option<f64>,
+    pub std: Option<f64>,
+    pub quantiles: Option<Quantiles>,
+    /// The most frequent values other than null, most frequent first.
+    pub top_values: Vec<ValueCount>,
+}
+
+/// Approximate quartiles.
+#[derive(TS, Serialize, Deserialize, Debug, Clone)]
+pub struct Quantiles {
+    pub q25: f64,
+    pub q50: f64,
+    pub q75: f64,
+}
+
+#[derive(TS, Serialize, Deserialize, Debug, Clone)]
+pub struct ValueCount {
+    pub value: serde_json::Value,
+    pub count: u64,
+}
+
+/// The distinct values of a column, to filter it by.
+#[derive(TS, Serialize, Deserialize, Debug, Clone)]
+pub struct ColumnValues {
+    /// Most frequent first, null included.
+    pub values: Vec<ValueCount>,
+    /// Whether there are more values than the limit.
+    pub has_more: bool,
+    /// For numeric and temporal columns, the number of values in buckets of
+    /// equal width between the smallest and the largest.
+    pub histogram: Option<Vec<HistogramBucket>>,
+}
+
+#[derive(TS, Serialize, Deserialize, Debug, Clone)]
+pub struct HistogramBucket {
+    /// `lower` is inclusive, `upper` only for the last bucket. Dates are
+    /// rounded down to the day.
+    pub lower: serde_json::Value,
+    pub upper: serde_json::Value,
+    pub count: u64,
+}
+
+/// Which values of a column to list, and how.
+pub struct ValuesOptions {
+    /// A column, or a dotted path into a struct column as in orderings.
+    pub column: String,
+    /// Ignore the filter's conditions on the column, so that the values are
+    /// those it could be changed to match.
+    pub exclude_column_filter: bool,
+    /// Only list values whose text starts with this, ignoring case.
+    pub prefix: Option<String>,
+    pub limit: u32,
+    /// 0 for no histogram.
+    pub buckets: u32,
+}
+
+/// A statistic `SUMMARIZE` gives as text, if the column is a number.
+pub fn parse_number(column_type: &ColumnType, value: Option<String>) -> Option<f64> {
+    match column_type.is_numeric() {
+        true => value?.parse().ok(),
+        false => None,
+    }
+}
@@ -0,0 +1,217 @@
+use regex::Regex;
+
+/// Columns every record of a text source gets, which capture groups cannot
+/// be named after.
+const GENERATED_COLUMNS: [&str; 2] = ["_line", "_raw"];
+
+/// Compile the pattern of a text source, rejecting group names that clash
+/// with the generated columns.
+pub fn compile_pattern(pattern: &str) -> anyhow::Result<Regex> {
+    let regex = Regex::new(pattern)?;
+    if let Some(name) = regex
+        .capture_names()
+        .flatten()
+        .find(|name| GENERATED_COLUMNS.contains(name))
+    {
+        return Err(anyhow::anyhow!(
+            "capture group `{}` clashes with the generated column of the same name",
+            name
+        ));
+    }
+    Ok(regex)
+}
+
+/// Result of parsing plain text with a regex.
+pub struct ParsedText {
+    pub ndjson: Vec<u8>,
+    pub line_count: usize,
+    pub unmatched_count: usize,
+}
+
+struct Line<'a> {
+    number: usize,
+    /// Captured values, one per named group, or `None` if the line did not
+    /// match.
+    values: Option<Vec<Option<&'a str>>>,
+    text: &'a str,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ColumnType {
+    Integer,
+    Float,
+    Text,
+}
+
+fn infer_column_type(values: &[Option<&str>]) -> ColumnType {
+    let mut column_type = ColumnType::Integer;
+    for value in values.iter().flatten() {
+        if column_type == ColumnType::Integer && value.parse::<i64>().is_err() {
+            column_type = ColumnType::Float;
+        }
+        if column_type == ColumnType::Float && value.parse::<f64>().is_err() {
+            return ColumnType::Text;
+        }
+    }
+    column_type
+}
+
+fn typed_value(value: Option<&str>, column_type: ColumnType) -> serde_json::Value {
+    match (value, column_type) {
+        (None, _) => serde_json::Value::Null,
+        (Some(v), ColumnType::Integer) => v.parse::<i64>().unwrap().into(),
+        (Some(v), ColumnType::Float) => serde_json::Number::from_f64(v.parse::<f64>().unwrap())
+            .map(serde_json::Value::Number)
+            .unwrap_or(serde_json::Value::Null),
+        (Some(v), ColumnType::Text) => v.into(),
+    }
+}
+
+/// Split `text` into lines and apply `regex` to each of them, producing one
+/// newline-delimited JSON record per non-empty line.
+///
+/// Every named capture group becomes a column. A column is emitted as an
+/// integer or float when every captured value parses as one, so that DuckDB
+/// infers a numeric type. Each record also carries `_line` (the 1-based line
+/// number) and `_raw`, which holds the original line for lines that did not
+/// match and is null otherwise.
+pub fn parse_lines(regex: &Regex, text: &str) -> anyhow::Result<ParsedText> {
+    let names = regex.capture_names().flatten().collect::<Vec<_>>();
+
+    let mut lines: Vec<Line> = vec![];
+    for (idx, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let values = regex.captures(line).map(|captures| {
+            names
+                .iter()
+                .map(|name| captures.name(name).map(|m| m.as_str()))
+                .collect::<Vec<_>>()
+        });
+        lines.push(Line {
+            number: idx + 1,
+            values,
+            text: line,
+        });
+    }
+
+    let column_types = (0..names.len())
+        .map(|i| {
+            let values = lines
+                .iter()
+                .filter_map(|line| line.values.as_ref().map(|v| v[i]))
+                .collect::<Vec<_>>();
+            infer_column_type(&values)
+        })
+        .collect::<Vec<_>>();
+
+    let mut ndjson = vec![];
+    let mut unmatched_count = 0;
+    for line in lines.iter() {
+        // Fields are written by hand rather than through a `serde_json::Map`
+        // so that columns keep the order of the capture groups.
+        let mut fields = vec![];
+        for (i, name) in names.iter().enumerate() {
+            let value = line.values.as_ref().and_then(|v| v[i]);
+            fields.push((*name, typed_value(value, column_types[i])));
+        }
+        fields.push(("_line", line.number.into()));
+        if line.values.is_none() {
+            unmatched_count += 1;
+            fields.push(("_raw", line.text.into()));
+        } else {
+            fields.push(("_raw", serde_json::Value::Null));
+        }
+
+        ndjson.push(b'{');
+        for (i, (name, value)) in fields.iter().enumerate() {
+            if i > 0 {
+                ndjson.push(b',');
+            }
+            serde_json::to_writer(&mut ndjson, name)?;
+            ndjson.push(b':');
+            serde_json::to_writer(&mut ndjson, value)?;
+        }
+        ndjson.extend_from_slice(b"}\n");
+    }
+
+    Ok(ParsedText {
+        ndjson,
+        line_count: lines.len(),
+        unmatched_count,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records(regex: &str, text: &str) -> (Vec<serde_json::Value>, ParsedText) {
+        let parsed = parse_lines(&compile_pattern(regex).unwrap(), text).unwrap();
+        let records = std::str::from_utf8(&parsed.ndjson)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        (records, parsed)
+    }
+
+    #[test]
+    fn infers_column_types() {
+        assert!(infer_column_type(&[Some("1"), Some("-2"), None]) == ColumnType::Integer);
+        assert!(infer_column_type(&[Some("1"), Some("2.5")]) == ColumnType::Float);
+        assert!(infer_column_type(&[Some("1.5"), Some("abc"), Some("2")]) == ColumnType::Text);
+        assert!(infer_column_type(&[None]) == ColumnType::Integer);
+        assert!(infer_column_type(&[]) == ColumnType::Integer);
+    }
+
+    #[test]
+    fn writes_typed_values_in_group_order() {
+        let (records, parsed) = records(
+            r"(?P<level>\w+) (?P<ms>[\d.]+) (?P<count>\d+)",
+            "INFO 1.5 3\nWARN 2 10\n",
+        );
+
+        assert_eq!(parsed.line_count, 2);
+        assert_eq!(parsed.unmatched_count, 0);
+        assert_eq!(
+            records[1],
+            serde_json::json!({"level": "WARN", "ms": 2.0, "count": 10, "_line": 2, "_raw": null})
+        );
+        let ndjson = String::from_utf8(parsed.ndjson).unwrap();
+        assert!(ndjson.starts_with(r#"{"level":"INFO","ms":1.5,"count":3,"_line":1"#));
+    }
+
+    #[test]
+    fn keeps_unmatched_lines_with_their_text() {
+        let (records, parsed) = records(
+            r"^(?P<level>[A-Z]+): (?P<code>\d+)?",
+            "INFO: 1\n\ngarbage here\nWARN: \n",
+        );
+
+        // Blank lines are skipped but still count towards line numbers.
+        assert_eq!(parsed.line_count, 3);
+        assert_eq!(parsed.unmatched_count, 1);
+        assert_eq!(
+            records[1],
+            serde_json::json!({"level": null, "code": null, "_line": 3, "_raw": "garbage here"})
+        );
+        // An optional group that did not participate is null, and does not
+        // stop the column from being an integer.
+        assert_eq!(
+            records[2],
+            serde_json::json!({"level": "WARN", "code": null, "_line": 4, "_raw": null})
+        );
+        assert_eq!(records[0]["code"], serde_json::json!(1));
+    }
+
+    #[test]
+    fn rejects_groups_named_after_generated_columns() {
+        for pattern in [r"(?P<_line>\d+)", r"(?P<x>\w+) (?P<_raw>.*)"] {
+            let error = compile_pattern(pattern).unwrap_err().to_string();
+            assert!(error.contains("clashes"), "{}", error);
+        }
+        assert!(compile_pattern("(?P<line>.*)").is_ok());
+    }
+}